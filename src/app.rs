@@ -1,24 +1,50 @@
-use crate::{config::Config, data::PageInfo, error::StapleError, template::Template};
+use crate::{
+    assets::MANIFEST_FILE,
+    cache::BuildCache,
+    config::{Config, DuplicateUrlPolicy},
+    constants::PUBLIC_FOLDER,
+    data::PageInfo,
+    error::StapleError,
+    feed, sitemap, taxonomy,
+    template::Template,
+};
+use rayon::prelude::*;
 use walkdir::WalkDir;
 
-use crate::data::DataFile;
-use std::path::{Path, PathBuf};
+use crate::data::{highlight, DataFile};
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug)]
 pub struct App {
     pub(crate) config: Config,
     pub(crate) template: Template,
     is_develop_mode: bool,
-    path: PathBuf,
+    pub(crate) path: PathBuf,
 }
 
 impl App {
     pub fn load(path: impl AsRef<Path>, develop: bool) -> Result<Self, StapleError> {
-        let config = Config::load_from_file(&path)?;
+        App::new(&path, Config::load_from_file(&path)?, develop)
+    }
+
+    pub fn new(
+        path: impl AsRef<Path>,
+        mut config: Config,
+        develop: bool,
+    ) -> Result<Self, StapleError> {
+        // keep output readable and live reload code untouched while developing
+        if develop {
+            config.build.minify = false;
+        }
+        if let Some(highlight) = &config.markdown.highlight {
+            highlight::get_theme(&highlight.theme)?;
+        }
         debug!("init template");
-        let theme = config.get_theme()?;
-        debug!("theme is {}", theme);
-        let template = Template::new(&path, theme)?;
+        let template = Template::new(&path, &config)?;
         Ok(Self {
             config,
             template,
@@ -43,13 +69,34 @@ impl App {
                 return Err(StapleError::HookError(x.to_cmd(), status.code()));
             }
         }
-        let vec = self
+        let vec: Vec<PageInfo> = self
             .load_all_data()?
             .into_iter()
             .filter(|article| !article.draw)
             .collect();
-        self.template
-            .render(vec, &self.config, self.is_develop_mode)?;
+        let cache = BuildCache::load(&self.path, self.is_develop_mode)?;
+        let mut current = cache.next();
+        self.template.render(
+            &vec,
+            &self.config,
+            self.is_develop_mode,
+            &cache,
+            &mut current,
+        )?;
+        if let Some(images) = &self.template.renderer.images {
+            images.process(self.path.join(PUBLIC_FOLDER), &cache, &mut current)?;
+        }
+        feed::render(
+            &vec,
+            &self.config,
+            &self.template.renderer,
+            &self.path,
+            &cache,
+            &mut current,
+        )?;
+        sitemap::render(&vec, &self.config, &self.path, &cache, &mut current)?;
+        cache.remove_stale(&current, self.path.join(PUBLIC_FOLDER))?;
+        current.save(&self.path)?;
 
         for x in &self.config.hook.after_build {
             info!("Before-Build Script: {}", x);
@@ -71,23 +118,116 @@ impl App {
     }
 
     pub fn load_all_data(&self) -> Result<Vec<PageInfo>, StapleError> {
+        let (articles, mut errors) = self.load_pages();
+        let (drafts, articles): (Vec<PageInfo>, Vec<PageInfo>) =
+            articles.into_iter().partition(|article| article.draw);
+        let (mut articles, duplicates) = self.deduplicate(articles);
+        errors.extend(duplicates);
+        errors.extend(self.term_collisions(&articles));
+        if !errors.is_empty() {
+            return Err(StapleError::PagesError(errors));
+        }
+        articles.extend(drafts);
+        articles.sort_by_key(|article| Reverse(article.datetime));
+        Ok(articles)
+    }
+
+    /// keep only the first page of those producing the same output file, e.g. `/a`, `/a/` and
+    /// `a/index.html`, assets of page bundles colliding with pages or each other are checked as
+    /// well. collisions are returned as errors unless `site.duplicate_url` is `warn`.
+    pub fn deduplicate(
+        &self,
+        articles: Vec<PageInfo>,
+    ) -> (Vec<PageInfo>, Vec<(String, StapleError)>) {
+        let mut outputs: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut kept = vec![];
+        for article in articles {
+            let files = outputs.entry(article.output_file_name()).or_default();
+            files.push(article.file.clone());
+            if files.len() == 1 {
+                kept.push(article);
+            }
+        }
+        if self.config.site.fingerprint {
+            let files = outputs.entry(MANIFEST_FILE.to_string()).or_default();
+            files.push("asset manifest".to_string());
+        }
+        // assets of bundles are copied next to their pages, like into output folder for `/a.html`
+        for (from, to) in self.template.bundle_assets(&kept) {
+            let files = outputs.entry(to).or_default();
+            files.push(from.to_string_lossy().to_string());
+        }
+
+        let mut errors = vec![];
+        for (output, files) in outputs.into_iter().filter(|(_, files)| files.len() > 1) {
+            match self.config.site.duplicate_url {
+                DuplicateUrlPolicy::Error => {
+                    errors.push((output.clone(), StapleError::DuplicateUrl { output, files }))
+                }
+                DuplicateUrlPolicy::Warn => warn!(
+                    "`{}` is produced by multiple files: {}, only {} is kept",
+                    output,
+                    files.join(", "),
+                    files[0]
+                ),
+            }
+        }
+        (kept, errors)
+    }
+
+    /// terms of taxonomies sharing the same url, returned as errors unless `site.duplicate_url`
+    /// is `warn`.
+    pub fn term_collisions(&self, articles: &[PageInfo]) -> Vec<(String, StapleError)> {
+        let mut errors = vec![];
+        for (url, names) in taxonomy::collisions(&self.config, articles) {
+            match self.config.site.duplicate_url {
+                DuplicateUrlPolicy::Error => {
+                    errors.push((url.clone(), StapleError::TermCollision { url, names }))
+                }
+                DuplicateUrlPolicy::Warn => warn!(
+                    "terms {} share the same url `{}`, their pages are listed together",
+                    names.join(", "),
+                    url
+                ),
+            }
+        }
+        errors
+    }
+
+    /// load all data files, pages that fail to load are returned as errors with file path.
+    pub fn load_pages(&self) -> (Vec<PageInfo>, Vec<(String, StapleError)>) {
         let data_path = self.path.join("data");
-        let mut articles = vec![];
-        let filter = WalkDir::new(data_path)
+        let files: Vec<PathBuf> = WalkDir::new(data_path)
+            .sort_by(|one, other| one.file_name().cmp(other.file_name()))
             .into_iter()
             .flat_map(|e| e.ok())
-            .filter(|de| de.path().is_file());
-
-        for path in filter {
-            let file_path = path.path();
-            if file_path.is_file() {
-                let datafile = DataFile::load(file_path)?;
-                if let Some(data) = datafile {
-                    articles.push(data);
+            .filter(|de| de.path().is_file())
+            .map(|de| de.into_path())
+            .collect();
+
+        let results: Vec<(&PathBuf, Result<Option<PageInfo>, StapleError>)> = files
+            .par_iter()
+            .map(|file_path| {
+                (
+                    file_path,
+                    DataFile::load(file_path, &self.template.renderer),
+                )
+            })
+            .collect();
+
+        let mut articles = vec![];
+        let mut errors = vec![];
+        for (file_path, result) in results {
+            match result {
+                Ok(Some(data)) => articles.push(data),
+                Ok(None) => {}
+                Err(e) => {
+                    let file = file_path.to_str().expect("invalid file path").to_string();
+                    errors.push((file, e));
                 }
             }
         }
         articles.sort_by(|one, other| other.datetime.cmp(&one.datetime));
-        Ok(articles)
+        (articles, errors)
     }
 }
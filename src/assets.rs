@@ -0,0 +1,113 @@
+use crate::config::{Config, Site};
+use std::{collections::BTreeMap, path::Path, sync::RwLock};
+
+/// manifest in output folder mapping statics to their fingerprinted files, kept in a folder of
+/// its own so that web app manifest `manifest.json` of site is not taken
+pub const MANIFEST_FILE: &str = ".staple/asset-manifest.json";
+
+/// length of content hash in names of fingerprinted files
+const FINGERPRINT_LENGTH: usize = 10;
+
+/// outputs of statics copied in current build, used by `asset_url` function
+#[derive(Debug)]
+pub struct Assets {
+    site: Site,
+    /// statics output like `statics/style.css` -> file actually generated
+    files: RwLock<BTreeMap<String, String>>,
+}
+
+impl Assets {
+    pub fn new(config: &Config) -> Self {
+        Assets {
+            site: Site {
+                domain_root: config.site.domain_root.clone(),
+                ..Default::default()
+            },
+            files: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn insert(&self, output: String, file: String) {
+        self.files
+            .write()
+            .expect("poisoned lock")
+            .insert(output, file);
+    }
+
+    /// url of the file generated for statics output, output not coming from statics is kept
+    pub fn url(&self, output: &str) -> String {
+        let output = output.trim_start_matches('/');
+        let files = self.files.read().expect("poisoned lock");
+        self.site
+            .root_url(files.get(output).map(String::as_str).unwrap_or(output))
+    }
+
+    /// json of statics outputs and their fingerprinted files
+    pub fn manifest(&self) -> String {
+        let files = self.files.read().expect("poisoned lock");
+        serde_json::to_string_pretty(&*files).expect("cannot serialize manifest")
+    }
+}
+
+/// files written for statics output, the output itself and its fingerprinted copy if `digest` is
+/// given, so that references not going through `asset_url` keep working
+pub fn outputs(output: &str, digest: Option<&str>) -> Vec<String> {
+    let mut outputs = vec![output.to_string()];
+    outputs.extend(digest.map(|digest| fingerprint(output, digest)));
+    outputs
+}
+
+/// output with content hash before extension, like `statics/style.3f9a2c1d0e.css`
+pub fn fingerprint(output: &str, digest: &str) -> String {
+    let hash = &digest[..FINGERPRINT_LENGTH.min(digest.len())];
+    let path = Path::new(output);
+    let name = match (
+        path.file_stem().and_then(|stem| stem.to_str()),
+        path.extension().and_then(|extension| extension.to_str()),
+    ) {
+        (Some(stem), Some(extension)) => format!("{}.{}.{}", stem, hash, extension),
+        _ => format!("{}.{}", output.rsplit('/').next().unwrap_or(output), hash),
+    };
+    match output.rfind('/') {
+        Some(index) => format!("{}/{}", &output[..index], name),
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        assets::{fingerprint, Assets},
+        config::Config,
+    };
+
+    #[test]
+    fn should_fingerprint_output() {
+        let digest = "3f9a2c1d0e4b5a6978";
+        assert_eq!(
+            "statics/style.3f9a2c1d0e.css",
+            fingerprint("statics/style.css", digest)
+        );
+        assert_eq!("a.tar.3f9a2c1d0e.gz", fingerprint("a.tar.gz", digest));
+        assert_eq!(
+            "statics/LICENSE.3f9a2c1d0e",
+            fingerprint("statics/LICENSE", digest)
+        );
+    }
+
+    #[test]
+    fn should_resolve_asset_url() {
+        let mut config = Config::default();
+        config.site.domain_root = "blog".to_string();
+        let assets = Assets::new(&config);
+        assets.insert(
+            "statics/style.css".to_string(),
+            "statics/style.3f9a2c1d0e.css".to_string(),
+        );
+        assert_eq!(
+            "/blog/statics/style.3f9a2c1d0e.css",
+            assets.url("/statics/style.css")
+        );
+        assert_eq!("/blog/statics/other.css", assets.url("statics/other.css"));
+    }
+}
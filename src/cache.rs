@@ -0,0 +1,259 @@
+use crate::{
+    constants::{BUILD_CACHE_FILE, CACHE_FOLDER, STAPLE_CONFIG_FILE},
+    error::StapleError,
+    images::ImageJobs,
+    util::hash::hash_bytes,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+/// state of the last successful build, used to skip pages and statics whose inputs did not change.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BuildCache {
+    /// digest of staple version, `Staple.toml` and build mode, any change of them leads to a full build
+    pub fingerprint: String,
+    /// output file of page -> cache entry
+    pub pages: HashMap<String, PageCache>,
+    /// output file of static -> digest of its source file
+    pub statics: HashMap<String, String>,
+    /// output file generated by build steps like feed -> digest of its content
+    #[serde(default)]
+    pub generated: HashMap<String, String>,
+    /// output of stylesheet in statics -> cache entry
+    #[serde(default)]
+    pub stylesheets: HashMap<String, StylesheetCache>,
+    /// processed image in output folder -> its source file
+    #[serde(default)]
+    pub images: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PageCache {
+    /// data file which the page comes from
+    pub source: String,
+    /// digest of data file, template dependency chain and (if used) page list
+    pub key: String,
+    /// digest of rendered content
+    pub output: String,
+    /// images resized while rendering the page
+    #[serde(default)]
+    pub images: ImageJobs,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StylesheetCache {
+    /// files the stylesheet is compiled from, including imports
+    pub sources: Vec<String>,
+    /// digest of source files
+    pub digest: String,
+    /// compiled css in output folder, which is fingerprinted if enabled
+    pub output: String,
+}
+
+impl BuildCache {
+    /// load cache of last build. cache file is deleted once loaded, so that a failed build
+    /// falls back to a full build next time.
+    pub fn load(path: impl AsRef<Path>, is_develop_mode: bool) -> Result<Self, StapleError> {
+        let fingerprint = BuildCache::fingerprint(&path, is_develop_mode)?;
+        let cache_file = path.as_ref().join(CACHE_FOLDER).join(BUILD_CACHE_FILE);
+        let cache = if cache_file.exists() {
+            let content = std::fs::read_to_string(&cache_file)?;
+            std::fs::remove_file(&cache_file)?;
+            serde_json::from_str::<BuildCache>(&content)
+                .ok()
+                .filter(|cache| cache.fingerprint == fingerprint)
+        } else {
+            None
+        };
+        if cache.is_none() {
+            debug!("build cache is missing or outdated, run full build");
+        }
+        Ok(cache.unwrap_or(BuildCache {
+            fingerprint,
+            ..Default::default()
+        }))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), StapleError> {
+        let cache_folder = path.as_ref().join(CACHE_FOLDER);
+        std::fs::create_dir_all(&cache_folder)?;
+        let content = serde_json::to_string(self)?;
+        std::fs::write(cache_folder.join(BUILD_CACHE_FILE), content)?;
+        Ok(())
+    }
+
+    fn fingerprint(path: impl AsRef<Path>, is_develop_mode: bool) -> Result<String, StapleError> {
+        let config = std::fs::read(path.as_ref().join(STAPLE_CONFIG_FILE))?;
+        Ok(hash_bytes(
+            [
+                env!("CARGO_PKG_VERSION").as_bytes(),
+                &config,
+                if is_develop_mode {
+                    b"develop"
+                } else {
+                    b"build"
+                },
+            ]
+            .concat(),
+        ))
+    }
+
+    /// whether nothing is cached, which means output folder needs to be built from scratch.
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+            && self.statics.is_empty()
+            && self.generated.is_empty()
+            && self.images.is_empty()
+    }
+
+    /// create an empty cache with the same fingerprint to record current build.
+    pub fn next(&self) -> BuildCache {
+        BuildCache {
+            fingerprint: self.fingerprint.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// write generated file into output folder if its content changes since last build.
+    pub fn write(
+        &mut self,
+        previous: &BuildCache,
+        output_folder: impl AsRef<Path>,
+        file: &str,
+        content: impl AsRef<[u8]>,
+    ) -> Result<(), StapleError> {
+        let digest = hash_bytes(&content);
+        let target = output_folder.as_ref().join(file);
+        if previous.generated.get(file) != Some(&digest) || !target.exists() {
+            if let Some(p) = target.parent() {
+                std::fs::create_dir_all(p)?;
+            }
+            std::fs::write(target, content)?;
+        }
+        self.generated.insert(file.to_string(), digest);
+        Ok(())
+    }
+
+    /// remove outputs which are produced by last build but not by current one.
+    pub fn remove_stale(
+        &self,
+        current: &BuildCache,
+        output_folder: impl AsRef<Path>,
+    ) -> Result<(), StapleError> {
+        let output_folder = output_folder.as_ref();
+        let is_produced = |file: &str| {
+            current.pages.contains_key(file)
+                || current.statics.contains_key(file)
+                || current.generated.contains_key(file)
+                || current.images.contains_key(file)
+        };
+
+        let stale_pages = self
+            .pages
+            .iter()
+            .filter(|(file, _)| !is_produced(file))
+            .map(|(file, page)| (file, Some(page)));
+        let stale_others = self
+            .statics
+            .keys()
+            .chain(self.generated.keys())
+            .chain(self.images.keys())
+            .filter(|file| !is_produced(file))
+            .map(|file| (file, None));
+
+        for (file, page) in stale_pages.chain(stale_others) {
+            let output = output_folder.join(file);
+            if !output.exists() {
+                continue;
+            }
+            match page {
+                Some(page) => info!("removing {} since {} is gone", file, page.source),
+                None => info!("removing {}", file),
+            }
+            std::fs::remove_file(&output)?;
+            let mut parent = output.parent();
+            while let Some(folder) = parent {
+                if folder == output_folder || std::fs::remove_dir(folder).is_err() {
+                    break;
+                }
+                parent = folder.parent();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cache::{BuildCache, PageCache},
+        constants::{BUILD_CACHE_FILE, CACHE_FOLDER},
+        test::setup,
+    };
+
+    #[test]
+    fn should_drop_cache_when_build_mode_changed() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+
+        let mut cache = BuildCache::load(&dir, false)?;
+        assert!(cache.is_empty());
+        cache.statics.insert("a.css".to_owned(), "hash".to_owned());
+        cache.save(&dir)?;
+
+        let cache = BuildCache::load(&dir, true)?;
+        assert!(cache.is_empty());
+        assert!(!dir.join(CACHE_FOLDER).join(BUILD_CACHE_FILE).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn should_load_saved_cache() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+
+        let mut cache = BuildCache::load(&dir, false)?;
+        let page = PageCache {
+            source: "data/a.md".to_owned(),
+            key: "key".to_owned(),
+            output: "output".to_owned(),
+            images: Default::default(),
+        };
+        cache.pages.insert("a/index.html".to_owned(), page.clone());
+        cache.save(&dir)?;
+
+        let cache = BuildCache::load(&dir, false)?;
+        assert_eq!(Some(&page), cache.pages.get("a/index.html"));
+        Ok(())
+    }
+
+    #[test]
+    fn should_remove_stale_output() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        std::fs::create_dir_all(dir.join("public/a"))?;
+        std::fs::write(dir.join("public/a/index.html"), "a")?;
+        std::fs::write(dir.join("public/b.css"), "b")?;
+
+        let mut cache = BuildCache::load(&dir, false)?;
+        cache.pages.insert(
+            "a/index.html".to_owned(),
+            PageCache {
+                source: "data/a.md".to_owned(),
+                key: "key".to_owned(),
+                output: "output".to_owned(),
+                images: Default::default(),
+            },
+        );
+        cache.statics.insert("b.css".to_owned(), "hash".to_owned());
+        let mut current = cache.next();
+        current
+            .statics
+            .insert("b.css".to_owned(), "hash".to_owned());
+
+        cache.remove_stale(&current, dir.join("public"))?;
+        assert!(!dir.join("public/a").exists());
+        assert!(dir.join("public/b.css").exists());
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     app::App,
     command::StapleCommand,
+    config::Config,
     data::types::{json::JsonFileData, markdown::MarkdownFileData, CreationOptions, FileType},
     error::StapleError,
 };
@@ -22,7 +23,8 @@ pub struct AddOptions {
 
 pub fn add(path: impl AsRef<Path>, options: AddOptions) -> Result<(), StapleError> {
     StapleCommand::lock_file(&path)?;
-    let app = App::load(&path, false)?;
+    // adding page does not touch statics
+    let app = App::new(&path, Config::load_from_file(&path)?, false)?;
     let url = options.url.clone().unwrap_or_else(|| {
         options
             .title
@@ -39,6 +41,7 @@ pub fn add(path: impl AsRef<Path>, options: AddOptions) -> Result<(), StapleErro
         url,
         template,
         draw: options.draw,
+        front_matter: app.config.markdown.front_matter,
     };
     if options.data {
         JsonFileData::create(path, &create_options)
@@ -1,18 +1,46 @@
-use crate::{app::App, command::StapleCommand, error::StapleError};
+use crate::{
+    app::App, command::StapleCommand, config::Config, constants::PUBLIC_FOLDER, error::StapleError,
+    link_checker::LinkChecker,
+};
 use std::path::Path;
 
-pub(crate) fn build(path: impl AsRef<Path>, develop: bool) -> Result<(), StapleError> {
+pub(crate) fn build(
+    path: impl AsRef<Path>,
+    develop: bool,
+    jobs: Option<usize>,
+) -> Result<(), StapleError> {
     StapleCommand::lock_file(&path)?;
-    App::load(&path, develop)?.render()
+    let mut pool = rayon::ThreadPoolBuilder::new();
+    if let Some(jobs) = jobs {
+        pool = pool.num_threads(jobs);
+    }
+    let path = path.as_ref().to_path_buf();
+    pool.build()?
+        .install(|| App::load(&path, develop)?.render())
+}
+
+/// check links of generated html files in output folder
+pub(crate) fn check_links(path: impl AsRef<Path>) -> Result<(), StapleError> {
+    let config = Config::load_from_file(&path)?;
+    info!("checking links");
+    let checker = LinkChecker::from_folder(path.as_ref().join(PUBLIC_FOLDER))?;
+    let broken = checker.check(&config.site);
+    if broken.is_empty() {
+        Ok(())
+    } else {
+        Err(StapleError::CheckError(broken))
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::{
+        assets::MANIFEST_FILE,
         command::{
             add::{add, AddOptions},
-            build::build,
+            build::{build, check_links},
         },
+        error::StapleError,
         test::setup,
     };
 
@@ -33,9 +61,9 @@ mod test {
         let string = std::fs::read_to_string(&article)?;
         let string1 = format!("{}\n\n{}", string, "# hello");
         std::fs::write(&article, string1)?;
-        build(&dir, false)?;
+        build(&dir, false, None)?;
 
-        let x = "<h1>hello</h1>\n";
+        let x = "<h1 id=\"hello\">hello</h1>\n";
         assert_eq!(
             x,
             std::fs::read_to_string(dir.join("public/test-markdown/index.html"))?
@@ -44,6 +72,718 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn should_only_render_changed_article() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        for title in &["one", "two"] {
+            let options = AddOptions {
+                title: title.to_string(),
+                url: None,
+                template: None,
+                draw: false,
+                data: false,
+            };
+            add(&dir, options)?;
+        }
+        build(&dir, false, None)?;
+
+        std::fs::write(dir.join("public/one/index.html"), "untouched")?;
+        std::fs::write(dir.join("public/two/index.html"), "untouched")?;
+        let article = dir.join("data/two.md");
+        let string = std::fs::read_to_string(&article)?;
+        std::fs::write(&article, format!("{}\n\n{}", string, "# hello"))?;
+        build(&dir, false, None)?;
+
+        assert_eq!(
+            "untouched",
+            std::fs::read_to_string(dir.join("public/one/index.html"))?
+        );
+        assert_eq!(
+            "<h1 id=\"hello\">hello</h1>\n",
+            std::fs::read_to_string(dir.join("public/two/index.html"))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_render_all_articles_when_template_changed() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let options = AddOptions {
+            title: "one".to_owned(),
+            url: None,
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(&dir, options)?;
+        build(&dir, false, None)?;
+
+        std::fs::write(
+            dir.join("templates/staple/article.html"),
+            "{{ page.title }}",
+        )?;
+        build(&dir, false, None)?;
+        assert_eq!(
+            "one",
+            std::fs::read_to_string(dir.join("public/one/index.html"))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_remove_output_of_deleted_article() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let options = AddOptions {
+            title: "one".to_owned(),
+            url: None,
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(&dir, options)?;
+        build(&dir, false, None)?;
+        assert!(dir.join("public/one/index.html").exists());
+
+        std::fs::remove_file(dir.join("data/one.md"))?;
+        build(&dir, false, None)?;
+        assert!(!dir.join("public/one").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn should_collect_errors_of_all_pages() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        for title in &["one", "two", "three"] {
+            let options = AddOptions {
+                title: title.to_string(),
+                url: None,
+                template: Some("missing.html".to_owned()),
+                draw: false,
+                data: false,
+            };
+            add(&dir, options)?;
+        }
+
+        match build(&dir, false, Some(2)) {
+            Err(StapleError::PagesError(errors)) => assert_eq!(3, errors.len()),
+            other => panic!("unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_all_malformed_articles() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        std::fs::write(dir.join("data/one.md"), "no meta here")?;
+        std::fs::write(dir.join("data/two.md"), " - title = two\n - = oops\n")?;
+
+        match build(&dir, false, None) {
+            Err(StapleError::PagesError(errors)) => {
+                let files: Vec<bool> = errors
+                    .iter()
+                    .map(|(file, _)| file.ends_with("one.md") || file.ends_with("two.md"))
+                    .collect();
+                assert_eq!(vec![true, true], files);
+                assert!(matches!(
+                    errors[0].1,
+                    StapleError::ArticleSyntaxError { line: 1, .. }
+                ));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_render_shortcodes_of_theme() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        std::fs::create_dir_all(dir.join("templates/staple/shortcodes"))?;
+        std::fs::write(
+            dir.join("templates/staple/shortcodes/quote.html"),
+            "<blockquote>{{ body | safe }}<cite>{{ by }}</cite></blockquote>",
+        )?;
+        let options = AddOptions {
+            title: "one".to_owned(),
+            url: None,
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(&dir, options)?;
+        let article = dir.join("data/one.md");
+        let meta = std::fs::read_to_string(&article)?;
+        std::fs::write(
+            &article,
+            format!("{}\n{{% quote(by=\"me\") %}}*hi*{{% end %}}\n", meta),
+        )?;
+        build(&dir, false, None)?;
+        assert_eq!(
+            "<blockquote><p><em>hi</em></p>\n<cite>me</cite></blockquote>",
+            std::fs::read_to_string(dir.join("public/one/index.html"))?
+        );
+
+        std::fs::write(&article, format!("{}\n{{{{ missing() }}}}\n", meta))?;
+        match build(&dir, false, None) {
+            Err(StapleError::PagesError(errors)) => {
+                assert!(errors[0].0.ends_with("one.md"));
+                assert!(matches!(errors[0].1, StapleError::ShortcodeError { .. }));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_copy_assets_of_page_bundle() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let options = AddOptions {
+            title: "post".to_owned(),
+            url: None,
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(&dir, options)?;
+        let meta = std::fs::read_to_string(dir.join("data/post.md"))?;
+        std::fs::remove_file(dir.join("data/post.md"))?;
+        std::fs::create_dir_all(dir.join("data/post/files"))?;
+        std::fs::create_dir_all(dir.join("data/post/sub"))?;
+        std::fs::write(
+            dir.join("data/post/index.md"),
+            format!(
+                "{}
+![diagram](diagram.png) [a](./files/a.txt) [b](b.txt) [sub](/post/sub)
+",
+                meta
+            ),
+        )?;
+        std::fs::write(dir.join("data/post/diagram.png"), "png")?;
+        std::fs::write(dir.join("data/post/files/a.txt"), "a")?;
+        std::fs::write(
+            dir.join("data/post/sub/index.md"),
+            meta.replace("url = post", "url = post/sub"),
+        )?;
+        std::fs::write(dir.join("data/post/sub/c.txt"), "c")?;
+        std::fs::write(dir.join("data/top.txt"), "top")?;
+        build(&dir, false, None)?;
+
+        assert_eq!(
+            "<p><img src=\"/post/diagram.png\" alt=\"diagram\" /> <a href=\"/post/files/a.txt\">a</a> <a href=\"b.txt\">b</a> <a href=\"/post/sub\">sub</a></p>\n",
+            std::fs::read_to_string(dir.join("public/post/index.html"))?
+        );
+        assert_eq!(
+            "png",
+            std::fs::read_to_string(dir.join("public/post/diagram.png"))?
+        );
+        assert_eq!(
+            "a",
+            std::fs::read_to_string(dir.join("public/post/files/a.txt"))?
+        );
+        assert_eq!(
+            "c",
+            std::fs::read_to_string(dir.join("public/post/sub/c.txt"))?
+        );
+        assert!(!dir.join("public/post/sub/index.md").exists());
+        assert!(!dir.join("public/post/c.txt").exists());
+        assert!(!dir.join("public/top.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn should_compile_stylesheets_of_theme() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let statics = dir.join("templates/staple/statics");
+        std::fs::create_dir_all(statics.join("sass"))?;
+        std::fs::write(statics.join("sass/_colors.scss"), "$main: red;")?;
+        std::fs::write(
+            statics.join("main.scss"),
+            "@import \"sass/colors\";\na { b { color: $main; } }",
+        )?;
+        build(&dir, false, None)?;
+        assert_eq!(
+            "a b {\n  color: red;\n}\n",
+            std::fs::read_to_string(dir.join("public/statics/main.css"))?
+        );
+        assert!(!dir.join("public/statics/main.scss").exists());
+        assert!(!dir.join("public/statics/sass").exists());
+
+        std::fs::write(statics.join("sass/_colors.scss"), "$main: blue;")?;
+        build(&dir, false, None)?;
+        assert_eq!(
+            "a b {\n  color: blue;\n}\n",
+            std::fs::read_to_string(dir.join("public/statics/main.css"))?
+        );
+
+        std::fs::write(statics.join("main.scss"), "a { color: $missing; }")?;
+        assert!(matches!(
+            build(&dir, false, None),
+            Err(StapleError::SassError { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn should_copy_statics_generated_by_hook() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let config = std::fs::read_to_string(dir.join("Staple.toml"))?;
+        std::fs::write(
+            dir.join("Staple.toml"),
+            config.replace(
+                "before_build = []",
+                &format!(
+                    "before_build = [{{ dir = {:?}, command = \"echo a > main.css\" }}]",
+                    dir.to_string_lossy()
+                ),
+            ) + "\n[[statics]]\nfrom = \"main.css\"\nto = \"main.css\"\n",
+        )?;
+        build(&dir, false, None)?;
+        assert_eq!("a\n", std::fs::read_to_string(dir.join("public/main.css"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn should_fingerprint_statics() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let config = std::fs::read_to_string(dir.join("Staple.toml"))?;
+        std::fs::write(
+            dir.join("Staple.toml"),
+            config
+                .replace("domain_root = \"\"", "domain_root = \"blog\"")
+                .replace("fingerprint = false", "fingerprint = true")
+                + "\n[[statics]]\nfrom = \"CNAME\"\nto = \"CNAME\"\n"
+                + "\n[[statics]]\nfrom = \"manifest.json\"\nto = \"manifest.json\"\n"
+                + "\n[[statics]]\nfrom = \"app.js\"\nto = \"app.js\"\nfingerprint = true\n",
+        )?;
+        std::fs::write(dir.join("CNAME"), "example.com")?;
+        std::fs::write(dir.join("manifest.json"), "{}")?;
+        std::fs::write(dir.join("app.js"), "a()")?;
+        let statics = dir.join("templates/staple/statics");
+        std::fs::create_dir_all(&statics)?;
+        std::fs::write(statics.join("style.css"), "a {}")?;
+        std::fs::write(statics.join("main.scss"), "b { c { d: e; } }")?;
+        std::fs::write(
+            dir.join("templates/staple/article.html"),
+            "{{ asset_url(path=\"statics/style.css\") | safe }} {{ asset_url(path=\"/statics/main.css\") | safe }}",
+        )?;
+        let options = AddOptions {
+            title: "one".to_owned(),
+            url: None,
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(&dir, options)?;
+        build(&dir, false, None)?;
+
+        let style =
+            crate::assets::fingerprint("statics/style.css", &crate::util::hash::hash_bytes("a {}"));
+        let main = std::fs::read_dir(dir.join("public/statics"))?
+            .flat_map(|e| e.ok())
+            .map(|e| format!("statics/{}", e.file_name().to_string_lossy()))
+            .find(|name| name.starts_with("statics/main.") && name != "statics/main.css")
+            .unwrap();
+        assert!(dir.join("public").join(&style).exists());
+        assert_eq!(
+            "a {}",
+            std::fs::read_to_string(dir.join("public/statics/style.css"))?
+        );
+        assert!(dir.join("public/statics/main.css").exists());
+        assert_eq!(
+            format!("/blog/{} /blog/{}", style, main),
+            std::fs::read_to_string(dir.join("public/one/index.html"))?
+        );
+        assert_eq!(
+            "example.com",
+            std::fs::read_to_string(dir.join("public/CNAME"))?
+        );
+        let app = crate::assets::fingerprint("app.js", &crate::util::hash::hash_bytes("a()"));
+        assert!(dir.join("public").join(&app).exists());
+        assert!(dir.join("public/app.js").exists());
+        let manifest: std::collections::HashMap<String, String> = serde_json::from_str(
+            &std::fs::read_to_string(dir.join("public").join(MANIFEST_FILE))?,
+        )?;
+        assert_eq!(Some(&style), manifest.get("statics/style.css"));
+        assert_eq!(
+            "{}",
+            std::fs::read_to_string(dir.join("public/manifest.json"))?
+        );
+
+        std::fs::write(statics.join("style.css"), "a { b: c }")?;
+        build(&dir, false, None)?;
+        let changed = crate::assets::fingerprint(
+            "statics/style.css",
+            &crate::util::hash::hash_bytes("a { b: c }"),
+        );
+        assert!(!dir.join("public").join(&style).exists());
+        assert!(dir.join("public").join(&changed).exists());
+        assert_eq!(
+            "a { b: c }",
+            std::fs::read_to_string(dir.join("public/statics/style.css"))?
+        );
+        assert!(std::fs::read_to_string(dir.join("public/one/index.html"))?.contains(&changed));
+
+        let site = crate::app::App::load(&dir, false)?;
+        let outputs = site.template.statics_outputs(&site.config);
+        for output in [&changed, &main, &app, "statics/style.css", "CNAME"] {
+            assert!(
+                outputs.iter().any(|o| o == output),
+                "{} in {:?}",
+                output,
+                outputs
+            );
+        }
+        for output in &outputs {
+            assert!(dir.join("public").join(output).exists(), "{}", output);
+        }
+
+        let options = AddOptions {
+            title: "two".to_owned(),
+            url: Some(format!("/{}", MANIFEST_FILE)),
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(&dir, options)?;
+        match build(&dir, false, None) {
+            Err(StapleError::PagesError(errors)) => match &errors[0].1 {
+                StapleError::DuplicateUrl { output, .. } => assert_eq!(MANIFEST_FILE, output),
+                other => panic!("unexpected error {:?}", other),
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_render_sections() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let page = |file: &str, metas: &[&str]| -> std::io::Result<()> {
+            let file = dir.join("data").join(file);
+            std::fs::create_dir_all(file.parent().unwrap())?;
+            let metas: String = metas.iter().map(|meta| format!(" - {}\n", meta)).collect();
+            std::fs::write(
+                file,
+                format!("{} - datetime = 2020-01-01T00:00:00+08:00\n", metas),
+            )
+        };
+        page(
+            "post/_index.md",
+            &[
+                "title = Posts",
+                "url = /post/",
+                "template = section.html",
+                "page_template = post.html",
+                "url_prefix = post",
+                "sort_by = title",
+            ],
+        )?;
+        page("post/b.md", &["title = b", "url = b"])?;
+        page("post/a.md", &["title = a", "url = a"])?;
+        page(
+            "post/2020/_index.md",
+            &[
+                "title = 2020",
+                "url = 2020/",
+                "template = section.html",
+                "page_draft = true",
+            ],
+        )?;
+        page("post/2020/c.md", &["title = c", "url = 2020/c"])?;
+        page(
+            "post/2020/d.md",
+            &["title = d", "url = 2020/d", "draw = false"],
+        )?;
+        std::fs::write(
+            dir.join("templates/staple/section.html"),
+            "{{ section.path | safe }}:{% for p in section.pages %}{{ p.title }},{% endfor %}\
+             {% for s in section.subsections %}[{{ s.index.title }}]{% endfor %}",
+        )?;
+        std::fs::write(
+            dir.join("templates/staple/post.html"),
+            "{% for a in ancestors %}{{ a.title }}/{% endfor %}{{ page.title }} {{ page.draw }}",
+        )?;
+        build(&dir, false, None)?;
+
+        assert_eq!(
+            "post:a,b,[2020]",
+            std::fs::read_to_string(dir.join("public/post/index.html"))?
+        );
+        assert_eq!(
+            "Posts/a false",
+            std::fs::read_to_string(dir.join("public/post/a/index.html"))?
+        );
+        assert_eq!(
+            "post/2020:d,",
+            std::fs::read_to_string(dir.join("public/post/2020/index.html"))?
+        );
+        assert_eq!(
+            "Posts/2020/d false",
+            std::fs::read_to_string(dir.join("public/post/2020/d/index.html"))?
+        );
+        assert!(!dir.join("public/post/2020/c/index.html").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn should_render_extra_output_formats() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let config = std::fs::read_to_string(dir.join("Staple.toml"))?;
+        std::fs::write(
+            dir.join("Staple.toml"),
+            config
+                + "\n[output_formats.json]\ntemplate = \"page.json\"\nsuffix = \"index.json\"\n"
+                + "\n[output_formats.amp]\ntemplate = \"amp.html\"\nsuffix = \"amp\"\n"
+                + "\n[[outputs]]\nsection = \"post\"\nformats = [\"json\"]\n",
+        )?;
+        std::fs::write(
+            dir.join("templates/staple/page.json"),
+            "{\"title\": \"{{ page.title }}\"}",
+        )?;
+        std::fs::write(
+            dir.join("templates/staple/amp.html"),
+            "amp {{ page.title }}",
+        )?;
+        for (title, url) in [
+            ("one", "/post/one"),
+            ("two", "/about"),
+            ("three", "/post/three"),
+        ] {
+            let options = AddOptions {
+                title: title.to_owned(),
+                url: Some(url.to_owned()),
+                template: None,
+                draw: false,
+                data: false,
+            };
+            add(&dir, options)?;
+        }
+        let file = dir.join("data/three.md");
+        let content = std::fs::read_to_string(&file)?;
+        std::fs::write(
+            &file,
+            content.replace(" - title = three", " - title = three\n - outputs = amp"),
+        )?;
+        build(&dir, false, None)?;
+
+        assert!(dir.join("public/post/one/index.html").exists());
+        assert_eq!(
+            "{\"title\": \"one\"}",
+            std::fs::read_to_string(dir.join("public/post/one/index.json"))?
+        );
+        assert!(!dir.join("public/about/index.json").exists());
+        assert!(!dir.join("public/post/three/index.json").exists());
+        assert_eq!(
+            "amp three",
+            std::fs::read_to_string(dir.join("public/post/three/amp/index.html"))?
+        );
+
+        std::fs::write(
+            dir.join("templates/staple/page.json"),
+            "{\"name\": \"{{ page.title }}\"}",
+        )?;
+        build(&dir, false, None)?;
+        assert_eq!(
+            "{\"name\": \"one\"}",
+            std::fs::read_to_string(dir.join("public/post/one/index.json"))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_minify_output_unless_developing() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let config = std::fs::read_to_string(dir.join("Staple.toml"))?;
+        std::fs::write(
+            dir.join("Staple.toml"),
+            format!("{}\n[build]\nminify = true\n", config),
+        )?;
+        std::fs::write(
+            dir.join("templates/staple/article.html"),
+            "<div class=\"page\">\n  <!-- content -->\n  <style> a { color : red ; } </style>\n  <script> var a = 1 ; </script>\n  {{ page.content.html | safe }}\n</div>\n",
+        )?;
+        let statics = dir.join("templates/staple/statics");
+        std::fs::create_dir_all(&statics)?;
+        std::fs::write(statics.join("a.css"), "a {\n  color: #ff0000;\n}\n")?;
+        std::fs::write(
+            statics.join("a.js"),
+            "function add(first, second) {\n  return first + second;\n}\n",
+        )?;
+        std::fs::write(statics.join("a.txt"), "a  b\n")?;
+        let options = AddOptions {
+            title: "one".to_owned(),
+            url: None,
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(&dir, options)?;
+        let article = dir.join("data/one.md");
+        let meta = std::fs::read_to_string(&article)?;
+        std::fs::write(&article, format!("{}\n# hello\n\n[top](#hello)\n", meta))?;
+
+        build(&dir, false, None)?;
+        assert_eq!(
+            "<div class=page><style>a{color:red}</style><script>var a=1;</script><h1 id=hello>hello</h1><p><a href=#hello>top</a></div>",
+            std::fs::read_to_string(dir.join("public/one/index.html"))?
+        );
+        assert_eq!(
+            "a{color:red}",
+            std::fs::read_to_string(dir.join("public/statics/a.css"))?
+        );
+        assert_eq!(
+            "function add(first,second){return first+second;}",
+            std::fs::read_to_string(dir.join("public/statics/a.js"))?
+        );
+        assert_eq!(
+            "a  b\n",
+            std::fs::read_to_string(dir.join("public/statics/a.txt"))?
+        );
+        check_links(&dir)?;
+
+        build(&dir, true, None)?;
+        let page = std::fs::read_to_string(dir.join("public/one/index.html"))?;
+        assert!(page.contains("<!-- content -->"));
+        assert_eq!(
+            "a {\n  color: #ff0000;\n}\n",
+            std::fs::read_to_string(dir.join("public/statics/a.css"))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_check_links_of_output() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let options = AddOptions {
+            title: "one".to_owned(),
+            url: None,
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(&dir, options)?;
+        let article = dir.join("data/one.md");
+        let content = std::fs::read_to_string(&article)?;
+        std::fs::write(
+            &article,
+            format!("{}# Title\n[ok](/one/#title) [broken](../two/)", content),
+        )?;
+        build(&dir, false, None)?;
+
+        match check_links(&dir) {
+            Err(StapleError::CheckError(broken)) => {
+                assert_eq!(1, broken.len());
+                assert_eq!("one/index.html", broken[0].0);
+                assert!(broken[0]
+                    .1
+                    .to_string()
+                    .contains("`two/index.html` is not generated"));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_detect_duplicate_url() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        for (title, url) in &[("one", "/same"), ("two", "same/index.html")] {
+            let options = AddOptions {
+                title: title.to_string(),
+                url: Some(url.to_string()),
+                template: None,
+                draw: false,
+                data: false,
+            };
+            add(&dir, options)?;
+        }
+
+        match build(&dir, false, None) {
+            Err(StapleError::PagesError(errors)) => match &errors[0].1 {
+                StapleError::DuplicateUrl { output, files } => {
+                    assert_eq!("same/index.html", output);
+                    assert_eq!(2, files.len());
+                }
+                other => panic!("unexpected error {:?}", other),
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let config_file = dir.join("Staple.toml");
+        let config = std::fs::read_to_string(&config_file)?;
+        std::fs::write(
+            &config_file,
+            config.replace(r#"duplicate_url = "error""#, r#"duplicate_url = "warn""#),
+        )?;
+        build(&dir, false, None)?;
+        assert!(dir.join("public/same/index.html").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn should_detect_bundle_assets_overwriting_pages() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        for title in &["one", "post"] {
+            let options = AddOptions {
+                title: title.to_string(),
+                url: None,
+                template: None,
+                draw: false,
+                data: false,
+            };
+            add(&dir, options)?;
+        }
+        let meta = std::fs::read_to_string(dir.join("data/post.md"))?;
+        std::fs::remove_file(dir.join("data/post.md"))?;
+        std::fs::create_dir_all(dir.join("data/post/one"))?;
+        std::fs::write(
+            dir.join("data/post/index.md"),
+            meta.replace("url = post", "url = /post.html"),
+        )?;
+        std::fs::write(dir.join("data/post/one/index.html"), "asset")?;
+
+        match build(&dir, false, None) {
+            Err(StapleError::PagesError(errors)) => match &errors[0].1 {
+                StapleError::DuplicateUrl { output, files } => {
+                    assert_eq!("one/index.html", output);
+                    assert_eq!(2, files.len());
+                    assert!(files[1].ends_with("index.html"), "{:?}", files);
+                }
+                other => panic!("unexpected error {:?}", other),
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let config_file = dir.join("Staple.toml");
+        let config = std::fs::read_to_string(&config_file)?;
+        std::fs::write(
+            &config_file,
+            config.replace(r#"duplicate_url = "error""#, r#"duplicate_url = "warn""#),
+        )?;
+        build(&dir, false, None)?;
+        assert_ne!(
+            "asset",
+            std::fs::read_to_string(dir.join("public/one/index.html"))?
+        );
+        Ok(())
+    }
+
     // todo should_render_json_content
     // todo metadata_should_be_rendered
 
@@ -0,0 +1,170 @@
+use crate::{
+    app::App,
+    assets::MANIFEST_FILE,
+    config::Config,
+    data::PageInfo,
+    error::StapleError,
+    link_checker::LinkChecker,
+    sitemap::{ROBOTS_FILE, SITEMAP_FILE},
+    template::SiteData,
+};
+use std::path::Path;
+
+/// validate the whole site by loading and rendering every page in memory, nothing is written
+/// into output folder. all problems including broken links are reported at once and fail the command.
+pub(crate) fn check(path: impl AsRef<Path>) -> Result<(), StapleError> {
+    let problems = problems(path)?;
+    if problems.is_empty() {
+        info!("everything looks fine");
+        Ok(())
+    } else {
+        Err(StapleError::CheckError(problems))
+    }
+}
+
+fn problems(path: impl AsRef<Path>) -> Result<Vec<(String, StapleError)>, StapleError> {
+    let config = Config::load_from_file(&path)?;
+    let statics = config.check_statics(&path);
+    let app = App::new(&path, config, false)?;
+    let (pages, mut problems) = app.load_pages();
+    let pages: Vec<PageInfo> = pages.into_iter().filter(|page| !page.draw).collect();
+    problems.extend(statics);
+    let (pages, duplicates) = app.deduplicate(pages);
+    problems.extend(duplicates);
+    problems.extend(app.term_collisions(&pages));
+    let site = SiteData::new(&pages, &app.config, false, &app.template.renderer);
+    let (outputs, errors) = app.template.check(&site);
+    problems.extend(errors);
+
+    let mut checker = LinkChecker::default();
+    for (output, content) in &outputs {
+        checker.add_page(output, content);
+    }
+    for output in app.template.statics_outputs(&app.config) {
+        checker.add_file(&output);
+    }
+    for (_, output) in app.template.bundle_assets(&pages) {
+        checker.add_file(&output);
+    }
+    for output in app
+        .template
+        .renderer
+        .images
+        .iter()
+        .flat_map(|i| i.outputs())
+    {
+        checker.add_file(&output);
+    }
+    for format in app.config.feed.iter().flat_map(|feed| feed.formats.iter()) {
+        checker.add_file(format.output_file());
+    }
+    if app.config.site.fingerprint {
+        checker.add_file(MANIFEST_FILE);
+    }
+    if !app.config.site.domain.is_empty() {
+        checker.add_file(SITEMAP_FILE);
+        checker.add_file(ROBOTS_FILE);
+    }
+    problems.extend(checker.check(&app.config.site));
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        command::{
+            add::{add, AddOptions},
+            check::check,
+        },
+        error::StapleError,
+        test::setup,
+    };
+
+    fn add_article(dir: &std::path::Path, title: &str, url: &str) -> Result<(), StapleError> {
+        let options = AddOptions {
+            title: title.to_owned(),
+            url: Some(url.to_owned()),
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(dir, options)
+    }
+
+    #[test]
+    fn should_pass_on_valid_site() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        add_article(&dir, "one", "one")?;
+        check(&dir)?;
+        assert!(!dir.join("public").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_all_problems() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let config_file = dir.join("Staple.toml");
+        let config = std::fs::read_to_string(&config_file)?;
+        std::fs::write(
+            &config_file,
+            config.replace(
+                "[extra]",
+                "[[statics]]\nfrom = \"missing.txt\"\nto = \"missing.txt\"\n\n[extra]",
+            ),
+        )?;
+        add_article(&dir, "one", "same")?;
+        add_article(&dir, "two", "/same/")?;
+        add_article(&dir, "three", "three")?;
+        let article = dir.join("data/three.md");
+        let content = std::fs::read_to_string(&article)?;
+        std::fs::write(&article, content.replace("article.html", "missing.html"))?;
+        add_article(&dir, "four", "four")?;
+        let article = dir.join("data/four.md");
+        let content = std::fs::read_to_string(&article)?;
+        let content: String = content
+            .lines()
+            .map(|line| {
+                if line.starts_with(" - datetime") {
+                    " - datetime = yesterday\n".to_string()
+                } else {
+                    format!("{}\n", line)
+                }
+            })
+            .collect();
+        std::fs::write(&article, content)?;
+        std::fs::write(
+            dir.join("templates/staple/detail.html"),
+            r#"{{ page_detail(file="data/not-exist.md") }}"#,
+        )?;
+        add_article(&dir, "five", "five")?;
+        let article = dir.join("data/five.md");
+        let content = std::fs::read_to_string(&article)?;
+        std::fs::write(&article, content.replace("article.html", "detail.html"))?;
+
+        match check(&dir) {
+            Err(StapleError::CheckError(problems)) => {
+                let messages: Vec<String> = problems
+                    .iter()
+                    .map(|(file, e)| format!("{}: {}", file, e))
+                    .collect();
+                assert_eq!(5, problems.len(), "{:#?}", messages);
+                assert!(
+                    messages[0].contains("four.md") && messages[0].contains("parse date error")
+                );
+                assert!(messages[1].contains("statics file `missing.txt` does not exist"));
+                assert!(messages[2].starts_with("same/index.html"));
+                assert!(messages
+                    .iter()
+                    .any(|m| m.contains("template `missing.html`")));
+                assert!(messages
+                    .iter()
+                    .any(|m| m.contains("Error on loading page detail")));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(!dir.join("public").exists());
+        Ok(())
+    }
+}
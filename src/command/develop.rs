@@ -16,7 +16,7 @@ use std::{
 
 pub(crate) fn develop(path: impl AsRef<Path>, port: u16) -> Result<(), StapleError> {
     StapleCommand::check_config_file_exist(&path)?;
-    crate::command::build::build(&path, true)?;
+    crate::command::build::build(&path, true, None)?;
 
     let has_new_file_event = Arc::new(AtomicBool::new(false));
     let _is_building = Arc::new(AtomicBool::new(false));
@@ -88,7 +88,7 @@ pub(crate) fn develop(path: impl AsRef<Path>, port: u16) -> Result<(), StapleErr
             file_event_flag_for_builder.compare_and_swap(true, false, Ordering::Relaxed);
         if need_build {
             info!("build stage is triggered by file event.");
-            let result1 = crate::command::build::build(buf.clone(), true);
+            let result1 = crate::command::build::build(buf.clone(), true, None);
             match result1 {
                 Ok(_) => info!("build successfully"),
                 Err(e) => error!("fail to build due to {}", e),
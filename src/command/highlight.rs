@@ -0,0 +1,66 @@
+use crate::{
+    config::Config,
+    data::highlight::{theme_css, theme_names},
+    error::StapleError,
+};
+use std::path::Path;
+
+/// export stylesheet of highlight theme, which is needed by `class` style highlighting.
+/// theme falls back to the one in `[markdown.highlight]`, and stylesheet is printed if no output given.
+pub(crate) fn command(
+    path: impl AsRef<Path>,
+    theme: Option<String>,
+    output: Option<String>,
+) -> Result<(), StapleError> {
+    let theme = match theme {
+        Some(theme) => theme,
+        None => {
+            Config::load_from_file(&path)
+                .ok()
+                .and_then(|config| config.markdown.highlight)
+                .unwrap_or_default()
+                .theme
+        }
+    };
+    let css = match theme_css(&theme) {
+        Ok(css) => css,
+        Err(e) => {
+            error!("available themes: {}", theme_names().join(", "));
+            return Err(e);
+        }
+    };
+    match output {
+        Some(output) => {
+            info!("writing stylesheet of theme {} into {}", theme, output);
+            std::fs::write(path.as_ref().join(output), css)?;
+        }
+        None => print!("{}", css),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{command::highlight::command, test::setup};
+
+    #[test]
+    fn should_export_stylesheet_of_configured_theme() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let config_file = dir.join("Staple.toml");
+        let config = std::fs::read_to_string(&config_file)?;
+        std::fs::write(
+            &config_file,
+            config.replace(
+                "[extra]",
+                "[markdown.highlight]\ntheme = \"base16-ocean.dark\"\nstyle = \"class\"\n\n[extra]",
+            ),
+        )?;
+        command(&dir, None, Some("highlight.css".to_string()))?;
+        let css = std::fs::read_to_string(dir.join("highlight.css"))?;
+        assert!(css.contains("theme \"Base16 Ocean Dark\""));
+
+        assert!(command(&dir, Some("not-exist".to_string()), None).is_err());
+        Ok(())
+    }
+}
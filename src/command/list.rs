@@ -1,10 +1,10 @@
-use crate::{app::App, command::StapleCommand, error::StapleError};
+use crate::{app::App, command::StapleCommand, config::Config, error::StapleError};
 use colored::*;
 use std::path::Path;
 
 pub(crate) fn command(path: impl AsRef<Path>) -> Result<(), StapleError> {
     StapleCommand::lock_file(&path)?;
-    let app = App::load(path, false)?;
+    let app = App::new(&path, Config::load_from_file(&path)?, false)?;
     info!("Project Name: {}", app.config.site.title);
     let mut pages = app.load_all_data()?;
     pages.reverse();
@@ -12,7 +12,9 @@ use crate::{
 
 pub mod add;
 pub mod build;
+pub mod check;
 pub mod develop;
+pub mod highlight;
 pub mod init;
 pub mod list;
 pub mod new;
@@ -34,18 +36,37 @@ pub enum StapleCommand {
     /// init current folder as Staple project.
     Init,
     /// build
-    Build,
+    Build {
+        /// number of worker threads used to load and render pages, default is the number of CPUs
+        #[structopt(short, long)]
+        jobs: Option<usize>,
+        /// check links in generated html files after building
+        #[structopt(long)]
+        check_links: bool,
+    },
     /// start the develop server listening on local with live-reload
     Develop {
         /// port of developing server listens on
         #[structopt(short, long, default_value = "8000", env = "STAPLE_DEVELOP_PORT")]
         port: u16,
     },
+    /// validate data, templates and statics without writing output, exit non-zero on any problem
+    Check,
     /// add new article
     Add(AddOptions),
 
     /// show all information of staple project
     List,
+
+    /// export stylesheet of highlight theme for `class` style highlighting
+    HighlightCss {
+        /// theme name, default is the one configured in `[markdown.highlight]`
+        #[structopt(short, long)]
+        theme: Option<String>,
+        /// file to write stylesheet into, default is printing to stdout
+        #[structopt(short, long)]
+        output: Option<String>,
+    },
 }
 
 impl StapleCommand {
@@ -54,14 +75,27 @@ impl StapleCommand {
         match self {
             StapleCommand::New { path, title, force } => new::new(path, title, force),
             StapleCommand::Init => init::init(&path),
-            StapleCommand::Build => build::build(path, false),
+            StapleCommand::Build { jobs, check_links } => {
+                build::build(path, false, jobs)?;
+                if check_links {
+                    build::check_links(path)?;
+                }
+                Ok(())
+            }
             StapleCommand::Develop { port } => develop::develop(&path, port),
+            StapleCommand::Check => {
+                StapleCommand::check_config_file_exist(path)?;
+                check::check(path)
+            }
             StapleCommand::List => {
                 StapleCommand::check_config_file_exist(&path)?;
                 list::command(&path)
             }
 
             StapleCommand::Add(options) => add::add(&path, options),
+            StapleCommand::HighlightCss { theme, output } => {
+                highlight::command(path, theme, output)
+            }
         }
     }
 
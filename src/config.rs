@@ -1,11 +1,20 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use serde_derive::{Deserialize, Serialize};
 use toml::Value;
 
-use crate::{constants::STAPLE_CONFIG_FILE, error::StapleError};
-use serde::export::Formatter;
-use std::fmt::Display;
+use crate::{
+    constants::STAPLE_CONFIG_FILE,
+    data::{extension::Registry, output_file_name},
+    error::StapleError,
+};
+use globset::{Glob, GlobBuilder, GlobSetBuilder};
+use pulldown_cmark::Options;
+use std::fmt::{Display, Formatter};
+use walkdir::WalkDir;
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct Config {
@@ -16,6 +25,21 @@ pub struct Config {
     pub hook: Hook,
     #[serde(default)]
     pub watch: Watch,
+    pub feed: Option<Feed>,
+    #[serde(default)]
+    pub taxonomies: Vec<Taxonomy>,
+    #[serde(default)]
+    pub markdown: Markdown,
+    #[serde(default)]
+    pub sass: Sass,
+    #[serde(default)]
+    pub build: Build,
+    /// formats pages can be rendered into besides their own template, like `[output_formats.json]`
+    #[serde(default)]
+    pub output_formats: HashMap<String, OutputFormat>,
+    /// formats rendered for pages of sections, pages declaring `outputs` field use their own
+    #[serde(default)]
+    pub outputs: Vec<Outputs>,
     pub extra: HashMap<String, Value>,
 }
 
@@ -95,6 +119,7 @@ impl Default for ConfigFile {
 }
 
 impl Config {
+    /// load config without checking statics, which may be generated by `before_build` hooks
     pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, StapleError> {
         debug!("load config file");
         let config_file_path = path.as_ref().join(STAPLE_CONFIG_FILE);
@@ -103,6 +128,15 @@ impl Config {
         Ok(result)
     }
 
+    /// missing sources and invalid patterns of `[[statics]]` entries
+    pub fn check_statics(&self, path: impl AsRef<Path>) -> Vec<(String, StapleError)> {
+        self.statics
+            .iter()
+            .filter_map(|statics| statics.files(&path).err())
+            .map(|e| (STAPLE_CONFIG_FILE.to_string(), e))
+            .collect()
+    }
+
     pub fn get_theme(&self) -> Result<String, StapleError> {
         Ok(self.site.theme.clone())
     }
@@ -121,6 +155,13 @@ impl Default for Config {
                 after_build: vec![],
             },
             watch: Default::default(),
+            feed: None,
+            taxonomies: vec![],
+            markdown: Default::default(),
+            sass: Default::default(),
+            build: Default::default(),
+            output_formats: Default::default(),
+            outputs: vec![],
             extra: Default::default(),
         }
     }
@@ -139,6 +180,23 @@ pub struct Site {
     pub domain: String,
     pub domain_root: String,
     pub default_template: String,
+    /// what to do when pages produce the same output file, `error` or `warn`
+    #[serde(default)]
+    pub duplicate_url: DuplicateUrlPolicy,
+    /// write copies of theme's statics with content hash in their names for cache busting, see
+    /// `asset_url` function. `[[statics]]` entries are fingerprinted only if they opt in
+    #[serde(default)]
+    pub fingerprint: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateUrlPolicy {
+    /// fail the build
+    #[default]
+    Error,
+    /// log a warning and keep only the newest page
+    Warn,
 }
 
 impl Default for Site {
@@ -155,19 +213,381 @@ impl Default for Site {
             domain: "".to_string(),
             domain_root: "".to_string(),
             default_template: "article.html".to_string(),
+            duplicate_url: DuplicateUrlPolicy::Error,
+            fingerprint: false,
+        }
+    }
+}
+
+impl Site {
+    /// absolute url of page with `domain` and `domain_root` prefixed
+    pub fn absolute_url(&self, url: &str) -> String {
+        format!(
+            "{}{}",
+            self.domain.trim_end_matches('/'),
+            self.root_url(url)
+        )
+    }
+
+    /// url of page with `domain_root` prefixed
+    pub fn root_url(&self, url: &str) -> String {
+        let url = url.trim_start_matches('/');
+        match self.domain_root.trim_matches('/') {
+            "" => format!("/{}", url),
+            root => format!("/{}/{}", root, url),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct Feed {
+    /// formats of feed to generate, `rss` for `rss.xml` and `atom` for `atom.xml`
+    pub formats: Vec<FeedFormat>,
+    /// max count of pages in feed
+    pub limit: usize,
+    /// use full content instead of description before `<!--more-->`
+    pub full_content: bool,
+    /// exclude pages whose field is set and not false, e.g. `data.page`
+    pub exclude: Option<String>,
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Self {
+            formats: vec![FeedFormat::Rss, FeedFormat::Atom],
+            limit: 20,
+            full_content: false,
+            exclude: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+}
+
+impl FeedFormat {
+    pub fn output_file(self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "rss.xml",
+            FeedFormat::Atom => "atom.xml",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Taxonomy {
+    /// name of taxonomy, terms are read from the field with same name in page's data
+    pub name: String,
+    /// url prefix of taxonomy pages, default is the name
+    pub url: Option<String>,
+    /// template of page listing all terms, rendered at `/<url>/`
+    pub index_template: Option<String>,
+    /// template of page listing pages of a term, rendered at `/<url>/<term>/`
+    pub template: Option<String>,
+}
+
+impl Taxonomy {
+    pub fn url_prefix(&self) -> String {
+        self.url
+            .as_deref()
+            .unwrap_or(&self.name)
+            .trim_matches('/')
+            .to_string()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Markdown {
+    /// highlight fenced code blocks at build time, disabled if absent
+    pub highlight: Option<Highlight>,
+    /// where to put anchor link of headings, `none`, `before` or `after` the heading text
+    pub anchor: HeadingAnchor,
+    /// style of metadata written by `staple add`, `inline`, `yaml` or `toml`
+    pub front_matter: FrontMatterStyle,
+    /// markdown extensions applied in order, built-in ones are `ruby` and `math`, only `ruby` is
+    /// enabled by default. `math` keeps tex for KaTeX or MathJax in browser, it is not rendered
+    /// into MathML at build time so pages using it need one of them
+    pub extensions: Vec<String>,
+    pub tables: bool,
+    pub footnotes: bool,
+    pub strikethrough: bool,
+    pub tasklists: bool,
+    /// fenced code blocks of these languages are diagrams instead of code, e.g. `[markdown.diagrams.mermaid]`
+    pub diagrams: HashMap<String, Diagram>,
+    /// give local images size and `srcset` of resized variants, disabled if absent
+    pub images: Option<ResponsiveImages>,
+}
+
+impl Default for Markdown {
+    fn default() -> Self {
+        Self {
+            highlight: None,
+            anchor: HeadingAnchor::default(),
+            front_matter: FrontMatterStyle::default(),
+            extensions: Registry::builtin()
+                .default_names()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            tasklists: true,
+            diagrams: HashMap::new(),
+            images: None,
+        }
+    }
+}
+
+impl Markdown {
+    /// syntax enabled in parser
+    pub fn options(&self) -> Options {
+        let mut options = Options::empty();
+        options.set(Options::ENABLE_TABLES, self.tables);
+        options.set(Options::ENABLE_FOOTNOTES, self.footnotes);
+        options.set(Options::ENABLE_STRIKETHROUGH, self.strikethrough);
+        options.set(Options::ENABLE_TASKLISTS, self.tasklists);
+        options
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct ResponsiveImages {
+    /// widths of resized variants in `srcset`, those not smaller than the image are skipped
+    pub widths: Vec<u32>,
+}
+
+impl Default for ResponsiveImages {
+    fn default() -> Self {
+        Self {
+            widths: vec![480, 960, 1440],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Diagram {
+    /// command reading diagram from stdin and printing html or svg, like `dot -Tsvg`, run by
+    /// `sh -c` or `cmd /C` on windows. diagram is kept as text for client side rendering like mermaid.js if absent
+    pub command: Option<String>,
+    /// class of wrapping `div`, default is the language
+    pub class: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontMatterStyle {
+    /// ` - key = value` lines
+    #[default]
+    Inline,
+    /// `---` wrapped yaml
+    Yaml,
+    /// `+++` wrapped toml
+    Toml,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadingAnchor {
+    #[default]
+    None,
+    Before,
+    After,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Highlight {
+    /// name of builtin syntect theme, like `InspiredGitHub` or `base16-ocean.dark`
+    pub theme: String,
+    /// `inline` puts colors into style attribute, `class` uses css classes exported by `staple highlight-css`
+    pub style: HighlightStyle,
+}
+
+impl Default for Highlight {
+    fn default() -> Self {
+        Self {
+            theme: "InspiredGitHub".to_string(),
+            style: HighlightStyle::Inline,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightStyle {
+    Inline,
+    Class,
+}
+
+/// post processing of build output, it is disabled in develop mode
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Build {
+    /// minify html pages along with their inline styles and scripts, and css and js of statics
+    pub minify: bool,
+}
+
+/// extra output of pages, like a json representation or an amp variant
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct OutputFormat {
+    /// template rendering pages in this format
+    pub template: String,
+    /// appended to url of page, `index.json` for `/post/index.json` or `amp` for `/post/amp/`
+    pub suffix: String,
+}
+
+impl OutputFormat {
+    /// output file of page at url, like `post/index.json` of `/post` or `/post.html`
+    pub fn output_file_name(&self, url: &str) -> String {
+        let url = url.trim_end_matches('/');
+        let base = match (Path::new(url).extension(), url.rfind('.')) {
+            (Some(_), Some(index)) => &url[..index],
+            _ => url,
+        };
+        output_file_name(&format!("{}/{}", base, self.suffix.trim_start_matches('/')))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Outputs {
+    /// url prefix of pages in section like `post`, empty for all pages
+    #[serde(default)]
+    pub section: String,
+    /// names of formats in `[output_formats]`
+    pub formats: Vec<String>,
+}
+
+impl Outputs {
+    /// whether page at url is in section, the most specific section is used for each page
+    pub fn contains(&self, url: &str) -> bool {
+        let section = self.section.trim_matches('/');
+        let url = url.trim_start_matches('/');
+        section.is_empty()
+            || url.trim_end_matches('/') == section
+            || url.starts_with(&format!("{}/", section))
+    }
+}
+
+/// compilation of `.scss` and `.sass` files in theme's statics folder
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Sass {
+    /// output compressed css
+    pub minify: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct Statics {
+    /// file, folder or glob pattern like `assets/**/*.png`, relative to site folder
     pub from: String,
+    /// output file of file, or output folder of folder and glob pattern
     pub to: String,
+    /// glob patterns of files to skip, matched against path relative to folder of `from`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// also write copies with content hash in their names once `site.fingerprint` is enabled,
+    /// disabled by default since files like `CNAME` and `robots.txt` are not used via `asset_url`
+    #[serde(default)]
+    pub fingerprint: bool,
+}
+
+impl Statics {
+    pub fn is_glob(&self) -> bool {
+        self.from.contains(['*', '?', '[', '{'])
+    }
+
+    /// folder or file before the first glob component of `from`
+    pub fn base(&self) -> String {
+        if !self.is_glob() {
+            return self.from.clone();
+        }
+        self.from
+            .split('/')
+            .take_while(|component| !component.contains(['*', '?', '[', '{']))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// source files and their output files relative to output folder
+    pub fn files(&self, path: impl AsRef<Path>) -> Result<Vec<(PathBuf, String)>, StapleError> {
+        let base = path.as_ref().join(self.base());
+        let to = self.to.trim_matches('/');
+        if !base.exists() {
+            return Err(StapleError::StaticsNotFound(self.from.clone()));
+        }
+        if base.is_file() {
+            return Ok(vec![(base, to.to_string())]);
+        }
+
+        let invalid = |e: globset::Error| StapleError::StaticsError {
+            from: self.from.clone(),
+            reason: e.to_string(),
+        };
+        let pattern = if self.is_glob() {
+            let glob = GlobBuilder::new(self.from.trim_start_matches("./"))
+                .literal_separator(true)
+                .build()
+                .map_err(invalid)?;
+            Some(glob.compile_matcher())
+        } else {
+            None
+        };
+        let mut excludes = GlobSetBuilder::new();
+        for exclude in &self.exclude {
+            excludes.add(Glob::new(exclude).map_err(invalid)?);
+        }
+        let excludes = excludes.build().map_err(invalid)?;
+
+        let mut files = vec![];
+        let entries = WalkDir::new(&base)
+            .sort_by(|one, other| one.file_name().cmp(other.file_name()))
+            .into_iter()
+            .flat_map(|e| e.ok())
+            .filter(|de| de.path().is_file());
+        for entry in entries {
+            let relative = slash_path(entry.path().strip_prefix(&base).unwrap_or(entry.path()));
+            let from = slash_path(entry.path().strip_prefix(&path).unwrap_or(entry.path()));
+            let is_matched = pattern
+                .as_ref()
+                .map(|pattern| pattern.is_match(&from))
+                .unwrap_or(true);
+            if is_matched && !excludes.is_match(&relative) {
+                let output = match to {
+                    "" => relative,
+                    to => format!("{}/{}", to, relative),
+                };
+                files.push((entry.into_path(), output));
+            }
+        }
+        Ok(files)
+    }
+}
+
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
 }
 
 #[cfg(test)]
 mod test {
-    use crate::config::{Config, ConfigFile, HookLine};
+    use crate::{
+        config::{
+            Config, ConfigFile, Feed, FeedFormat, HookLine, OutputFormat, Outputs, Site, Statics,
+        },
+        error::StapleError,
+        test::setup,
+    };
 
     #[test]
     fn test_hook_display() {
@@ -218,4 +638,142 @@ mod test {
         let config = Config::get_default_file();
         assert_eq!(ConfigFile::default(), config);
     }
+
+    #[test]
+    fn test_site_absolute_url() {
+        let mut site = Site {
+            domain: "https://example.com/".to_string(),
+            ..Default::default()
+        };
+        assert_eq!("https://example.com/", site.absolute_url("/"));
+        assert_eq!("https://example.com/a/b", site.absolute_url("a/b"));
+
+        site.domain_root = "/blog/".to_string();
+        assert_eq!("https://example.com/blog/", site.absolute_url("/"));
+        assert_eq!(
+            "https://example.com/blog/rss.xml",
+            site.absolute_url("/rss.xml")
+        );
+    }
+
+    #[test]
+    fn test_config_feed_default() {
+        let config: Config = toml::from_str(
+            r#"
+            [site]
+            title = ""
+            subtitle = ""
+            description = ""
+            keywords = []
+            author = ""
+            email = ""
+            utc_offset = 800
+            theme = "staple"
+            domain = ""
+            domain_root = ""
+            default_template = "article.html"
+
+            [feed]
+            formats = ["atom"]
+
+            [extra]
+            "#,
+        )
+        .unwrap();
+        let feed = config.feed.unwrap();
+        assert_eq!(vec![FeedFormat::Atom], feed.formats);
+        assert_eq!(Feed::default().limit, feed.limit);
+        assert!(Config::default().feed.is_none());
+    }
+
+    #[test]
+    fn test_statics_files() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        std::fs::create_dir_all(dir.join("assets/img"))?;
+        for file in &[
+            "assets/a.css",
+            "assets/a.psd",
+            "assets/img/b.png",
+            "assets/img/c.psd",
+        ] {
+            std::fs::write(dir.join(file), "")?;
+        }
+        let statics = |from: &str, to: &str, exclude: &[&str]| Statics {
+            from: from.to_string(),
+            to: to.to_string(),
+            exclude: exclude.iter().map(|e| e.to_string()).collect(),
+            fingerprint: false,
+        };
+        let outputs = |statics: Statics| -> Result<Vec<String>, StapleError> {
+            Ok(statics
+                .files(&dir)?
+                .into_iter()
+                .map(|(_, output)| output)
+                .collect())
+        };
+
+        assert_eq!(
+            vec!["a/b.css"],
+            outputs(statics("assets/a.css", "/a/b.css", &[]))?
+        );
+        assert_eq!(
+            vec!["static/a.css", "static/img/b.png"],
+            outputs(statics("assets", "static/", &["*.psd"]))?
+        );
+        assert_eq!(vec!["a.css"], outputs(statics("assets/*.css", "", &[]))?);
+        assert_eq!(
+            vec!["img/img/b.png"],
+            outputs(statics("assets/**/*.png", "img", &[]))?
+        );
+        assert!(matches!(
+            statics("missing/*.css", "", &[]).files(&dir),
+            Err(StapleError::StaticsNotFound(_))
+        ));
+        assert!(matches!(
+            statics("assets/[a.css", "", &[]).files(&dir),
+            Err(StapleError::StaticsError { .. })
+        ));
+
+        crate::command::init::init(&dir)?;
+        let config = std::fs::read_to_string(dir.join("Staple.toml"))?;
+        std::fs::write(
+            dir.join("Staple.toml"),
+            config + "\n[[statics]]\nfrom = \"missing.txt\"\nto = \"missing.txt\"\n",
+        )?;
+        let config = Config::load_from_file(&dir)?;
+        let problems = config.check_statics(&dir);
+        assert_eq!(1, problems.len());
+        let error = problems[0].1.to_string();
+        assert!(
+            error.contains("statics file `missing.txt` does not exist"),
+            "{}",
+            error
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_formats() {
+        let format = OutputFormat {
+            template: "post.json".to_string(),
+            suffix: "index.json".to_string(),
+        };
+        assert_eq!("post/index.json", format.output_file_name("/post"));
+        assert_eq!("post/index.json", format.output_file_name("/post/"));
+        assert_eq!("post/index.json", format.output_file_name("/post.html"));
+        assert_eq!("index.json", format.output_file_name("/"));
+        let amp = OutputFormat {
+            template: "amp.html".to_string(),
+            suffix: "amp".to_string(),
+        };
+        assert_eq!("post/amp/index.html", amp.output_file_name("/post"));
+
+        let outputs = Outputs {
+            section: "/post/".to_string(),
+            formats: vec!["json".to_string()],
+        };
+        assert!(outputs.contains("/post/one"));
+        assert!(outputs.contains("/post/"));
+        assert!(!outputs.contains("/posts/one"));
+    }
 }
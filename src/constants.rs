@@ -11,8 +11,9 @@ pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
 pub const STAPLE_CONFIG_FILE: &str = "Staple.toml";
 pub const STAPLE_LOCK_FILE: &str = "Staple.lock";
 
-pub const RENDER_FOLDER: &str = ".render";
 pub const PUBLIC_FOLDER: &str = "public";
+pub const CACHE_FOLDER: &str = ".staple";
+pub const BUILD_CACHE_FILE: &str = "build-cache.json";
 
 pub const DESCRIPTION_SEPARATOR: &str = "<!--more-->";
 
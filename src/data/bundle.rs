@@ -0,0 +1,175 @@
+use crate::{
+    config::{Config, Site},
+    data::output_file_name,
+};
+use pulldown_cmark::{CowStr, Event, Tag};
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+/// recognizes page bundles in data folder, like `data/post/index.md` along with
+/// `data/post/diagram.png`
+#[derive(Debug)]
+pub struct Bundles {
+    data_folder: PathBuf,
+    site: Site,
+}
+
+impl Bundles {
+    pub fn new(path: impl AsRef<Path>, config: &Config) -> Self {
+        Bundles {
+            data_folder: path.as_ref().join("data"),
+            site: Site {
+                domain_root: config.site.domain_root.clone(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// bundle of page whose file is `index.md` or `index.json` in a sub folder of data folder
+    pub fn find(&self, file: impl AsRef<Path>, url: &str) -> Option<Bundle> {
+        let file = file.as_ref();
+        let is_index = file.file_stem().and_then(|stem| stem.to_str()) == Some("index");
+        let folder = file.parent()?;
+        if !is_index || !is_page(file) || folder == self.data_folder {
+            return None;
+        }
+        let output_file = output_file_name(url);
+        let output = match output_file.rfind('/') {
+            Some(index) => output_file[..=index].to_string(),
+            None => String::new(),
+        };
+        Some(Bundle {
+            folder: folder.to_path_buf(),
+            url: self.site.root_url(&output),
+            output,
+        })
+    }
+}
+
+/// folder of page bundle, files other than pages are assets copied next to output of the page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bundle {
+    pub folder: PathBuf,
+    /// url of folder the page is generated into, like `/post/`
+    pub url: String,
+    /// folder the page is generated into, relative to output folder like `post/`
+    pub output: String,
+}
+
+impl Bundle {
+    /// paths of assets relative to bundle folder, sub folders being bundles themselves are skipped
+    pub fn assets(&self) -> Vec<PathBuf> {
+        WalkDir::new(&self.folder)
+            .sort_by(|one, other| one.file_name().cmp(other.file_name()))
+            .into_iter()
+            .filter_entry(|de| de.path() == self.folder || !is_bundle_folder(de.path()))
+            .flat_map(|e| e.ok())
+            .filter(|de| de.path().is_file() && !is_page(de.path()))
+            .filter_map(|de| Some(de.path().strip_prefix(&self.folder).ok()?.to_path_buf()))
+            .collect()
+    }
+
+    /// output files of assets relative to output folder
+    pub fn outputs(&self) -> Vec<(PathBuf, String)> {
+        self.assets()
+            .into_iter()
+            .map(|asset| {
+                let relative = asset
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                (
+                    self.folder.join(asset),
+                    format!("{}{}", self.output, relative),
+                )
+            })
+            .collect()
+    }
+
+    /// source file of asset linked relatively, like `diagram.png` or `./files/a.pdf`
+    fn asset(&self, link: &str) -> Option<PathBuf> {
+        let path = link.split(['?', '#']).next().unwrap_or_default();
+        let path = percent_encoding::percent_decode_str(path)
+            .decode_utf8()
+            .ok()?;
+        let path = Path::new(path.as_ref());
+        let is_relative = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        let file = self.folder.join(path);
+        if path.as_os_str().is_empty() || !is_relative || !file.is_file() || is_page(&file) {
+            return None;
+        }
+        Some(file)
+    }
+
+    /// source file of asset by its url
+    pub fn resolve_url(&self, url: &str) -> Option<PathBuf> {
+        self.asset(url.strip_prefix(self.url.as_str())?)
+    }
+
+    /// point relative links and images to assets at url of the page, so that they work
+    /// no matter the page's url ends with slash or not
+    pub fn rewrite_link<'a>(&self, event: Event<'a>) -> Event<'a> {
+        match event {
+            Event::Start(tag) => Event::Start(self.rewrite_tag(tag)),
+            Event::End(tag) => Event::End(self.rewrite_tag(tag)),
+            other => other,
+        }
+    }
+
+    fn rewrite_tag<'a>(&self, tag: Tag<'a>) -> Tag<'a> {
+        match tag {
+            Tag::Link(link_type, url, title) => Tag::Link(link_type, self.rewrite(url), title),
+            Tag::Image(link_type, url, title) => Tag::Image(link_type, self.rewrite(url), title),
+            other => other,
+        }
+    }
+
+    fn rewrite<'a>(&self, link: CowStr<'a>) -> CowStr<'a> {
+        match self.asset(&link) {
+            Some(_) => format!("{}{}", self.url, link.trim_start_matches("./")).into(),
+            None => link,
+        }
+    }
+}
+
+fn is_page(file: &Path) -> bool {
+    matches!(
+        file.extension().and_then(|e| e.to_str()),
+        Some("md") | Some("json")
+    )
+}
+
+pub(crate) fn is_bundle_folder(path: &Path) -> bool {
+    path.join("index.md").is_file() || path.join("index.json").is_file()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{config::Config, data::bundle::Bundles, test::setup};
+
+    #[test]
+    fn should_find_bundle_of_index_page() {
+        let dir = setup();
+        let mut config = Config::default();
+        config.site.domain_root = "blog".to_string();
+        let bundles = Bundles::new(&dir, &config);
+
+        let bundle = bundles
+            .find(dir.join("data/post/index.md"), "/post")
+            .unwrap();
+        assert_eq!(dir.join("data/post"), bundle.folder);
+        assert_eq!("/blog/post/", bundle.url);
+        assert_eq!("post/", bundle.output);
+        let bundle = bundles
+            .find(dir.join("data/a/index.json"), "/a.html")
+            .unwrap();
+        assert_eq!("/blog/", bundle.url);
+        assert_eq!("", bundle.output);
+
+        assert_eq!(None, bundles.find(dir.join("data/index.md"), "/"));
+        assert_eq!(None, bundles.find(dir.join("data/post/other.md"), "/other"));
+    }
+}
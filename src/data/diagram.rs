@@ -0,0 +1,212 @@
+use crate::{config::Diagram, data::highlight::FenceInfo, error::StapleError};
+use pulldown_cmark::{CowStr, Event, Tag};
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, Write},
+    process::{Command, Stdio},
+    sync::Mutex,
+};
+
+/// outputs of diagram commands by command and source, pages are loaded more than once in a
+/// build. it lives in renderer of a build so that rebuilds of develop mode run commands again.
+#[derive(Debug, Default)]
+pub struct DiagramOutputs(Mutex<HashMap<(String, String), String>>);
+
+impl DiagramOutputs {
+    fn get_or_run(&self, command: &str, source: &str) -> Result<String, String> {
+        let key = (command.to_string(), source.to_string());
+        if let Some(output) = self.0.lock().expect("poisoned lock").get(&key) {
+            return Ok(output.clone());
+        }
+        let output = run(command, source)?;
+        self.0
+            .lock()
+            .expect("poisoned lock")
+            .insert(key, output.clone());
+        Ok(output)
+    }
+}
+
+/// replace fenced code blocks of configured diagram languages with html
+pub fn render_diagrams<'a>(
+    events: impl Iterator<Item = Event<'a>>,
+    diagrams: &HashMap<String, Diagram>,
+    outputs: &DiagramOutputs,
+) -> Result<Vec<Event<'a>>, StapleError> {
+    let mut output = vec![];
+    let mut block: Option<(String, String)> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(info)) => match diagram_language(&info, diagrams) {
+                Some(language) => block = Some((language, String::new())),
+                None => output.push(Event::Start(Tag::CodeBlock(info))),
+            },
+            Event::End(Tag::CodeBlock(_)) if block.is_some() => {
+                let (language, source) = block.take().expect("diagram block is missing");
+                let html = render(&language, &source, &diagrams[&language], outputs)?;
+                output.push(Event::Html(html.into()));
+            }
+            Event::Text(text) if block.is_some() => {
+                if let Some((_, source)) = block.as_mut() {
+                    source.push_str(&text);
+                }
+            }
+            other => output.push(other),
+        }
+    }
+    Ok(output)
+}
+
+fn diagram_language(info: &CowStr, diagrams: &HashMap<String, Diagram>) -> Option<String> {
+    FenceInfo::parse(info)
+        .language
+        .filter(|language| diagrams.contains_key(language))
+}
+
+fn render(
+    language: &str,
+    source: &str,
+    diagram: &Diagram,
+    outputs: &DiagramOutputs,
+) -> Result<String, StapleError> {
+    let content = match &diagram.command {
+        Some(command) => {
+            outputs
+                .get_or_run(command, source)
+                .map_err(|reason| StapleError::DiagramError {
+                    language: language.to_string(),
+                    reason,
+                })?
+        }
+        None => tera::escape_html(source),
+    };
+    let class = diagram.class.as_deref().unwrap_or(language);
+    Ok(format!(
+        "<div class=\"{}\">{}</div>\n",
+        tera::escape_html(class),
+        content
+    ))
+}
+
+/// output of command with source as stdin, xml prolog before `<svg` is dropped for inlining
+fn run(command: &str, source: &str) -> Result<String, String> {
+    info!("running diagram command `{}`", command);
+    let mut child = shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot run `{}`: {}", command, e))?;
+    // written in another thread so that command printing before reading all input won't block
+    let writer = child.stdin.take().map(|mut stdin| {
+        let source = source.to_string();
+        std::thread::spawn(move || stdin.write_all(source.as_bytes()))
+    });
+    let result = child
+        .wait_with_output()
+        .map_err(|e| format!("cannot run `{}`: {}", command, e))?;
+    if !result.status.success() {
+        return Err(format!(
+            "`{}` exits with code {}: {}",
+            command,
+            result.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(&result.stderr).trim()
+        ));
+    }
+    match writer.map(|writer| writer.join()) {
+        Some(Ok(Err(e))) if e.kind() != ErrorKind::BrokenPipe => {
+            return Err(format!("cannot write into `{}`: {}", command, e));
+        }
+        _ => {}
+    }
+    let output = String::from_utf8_lossy(&result.stdout);
+    Ok(match output.find("<svg") {
+        Some(start) => output[start..].trim_end().to_string(),
+        None => output.trim_end().to_string(),
+    })
+}
+
+/// command run by shell of platform, `cmd /C` on windows and `sh -c` elsewhere
+fn shell(command: &str) -> Command {
+    let (program, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut shell = Command::new(program);
+    shell.arg(flag).arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::{Diagram, Markdown},
+        data::{MarkdownContent, MarkdownRenderer},
+        test::setup,
+    };
+
+    fn renderer() -> MarkdownRenderer {
+        let mut config = Markdown {
+            highlight: Some(Default::default()),
+            ..Default::default()
+        };
+        config
+            .diagrams
+            .insert("mermaid".to_string(), Diagram::default());
+        let svg = Diagram {
+            command: Some("printf '<?xml?>'; sed s/a/b/".to_string()),
+            class: Some("diagram".to_string()),
+        };
+        config.diagrams.insert("svg".to_string(), svg);
+        let fail = Diagram {
+            command: Some("echo oops >&2; exit 3".to_string()),
+            class: None,
+        };
+        config.diagrams.insert("fail".to_string(), fail);
+        MarkdownRenderer::new(config).unwrap()
+    }
+
+    #[test]
+    fn should_render_diagram_blocks() {
+        let content = MarkdownContent::render(
+            "```mermaid\ngraph TD; A-->B\n```\n\n```svg\n<svg>a</svg>\n```\n".to_string(),
+            &renderer(),
+        )
+        .unwrap();
+        assert_eq!(
+            "<div class=\"mermaid\">graph TD; A--&gt;B\n</div>\n<div class=\"diagram\"><svg>b</svg></div>\n",
+            content.html
+        );
+    }
+
+    #[test]
+    fn should_report_failed_diagram_command() {
+        let error = MarkdownContent::render("```fail\nx\n```\n".to_string(), &renderer())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("exits with code 3: oops"), "{}", error);
+    }
+
+    #[test]
+    fn should_run_diagram_command_once_per_renderer() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        let log = dir.join("runs");
+        let mut config = Markdown::default();
+        let count = Diagram {
+            command: Some(format!("echo run >> '{}'; cat", log.display())),
+            class: None,
+        };
+        config.diagrams.insert("count".to_string(), count);
+        let source = "```count\nx\n```\n\n```count\nx\n```\n";
+
+        let renderer = MarkdownRenderer::new(config.clone())?;
+        MarkdownContent::render(source.to_string(), &renderer)?;
+        MarkdownContent::render(source.to_string(), &renderer)?;
+        assert_eq!("run\n", std::fs::read_to_string(&log)?);
+
+        MarkdownContent::render(source.to_string(), &MarkdownRenderer::new(config)?)?;
+        assert_eq!("run\nrun\n", std::fs::read_to_string(&log)?);
+        Ok(())
+    }
+}
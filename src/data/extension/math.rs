@@ -0,0 +1,155 @@
+use crate::data::{
+    extension::MarkdownExtension,
+    placeholder::{code_ranges, in_code, Placeholders},
+};
+use pulldown_cmark::Options;
+
+/// tex math `$inline$` and `$$display$$`, kept away from markdown and rendered as
+/// `\(...\)` and `\[...\]` which KaTeX auto-render and MathJax recognize. tex is not converted
+/// into MathML here, which takes a tex engine either as a dependency or a javascript runtime
+#[derive(Debug)]
+pub struct Math;
+
+impl MarkdownExtension for Math {
+    fn name(&self) -> &'static str {
+        "math"
+    }
+
+    fn is_default(&self) -> bool {
+        false
+    }
+
+    fn preprocess(&self, raw: String, options: Options, placeholders: &mut Placeholders) -> String {
+        let code = code_ranges(&raw, options);
+        let bytes = raw.as_bytes();
+        let mut output = String::new();
+        let mut position = 0;
+        let mut index = 0;
+        while index < bytes.len() {
+            if in_code(&code, index) {
+                index += 1;
+                continue;
+            }
+            match bytes[index] {
+                b'\\' => index += 2,
+                b'$' => match find_math(&raw, index) {
+                    Some((tex, end, display)) => {
+                        output.push_str(&raw[position..index]);
+                        output
+                            .push_str(&placeholders.insert(render(tex, display), tex.to_string()));
+                        position = end;
+                        index = end;
+                    }
+                    None if raw[index..].starts_with("$$") => index += 2,
+                    None => index += 1,
+                },
+                _ => index += 1,
+            }
+        }
+        output.push_str(&raw[position..]);
+        output
+    }
+}
+
+/// tex, end of math and whether it is display math.
+/// like pandoc, opening `$` cannot be followed by whitespace and closing `$` cannot be
+/// preceded by whitespace or followed by digit, so that `$5 and $10` is not math.
+fn find_math(raw: &str, start: usize) -> Option<(&str, usize, bool)> {
+    let rest = &raw[start..];
+    if let Some(body) = rest.strip_prefix("$$") {
+        let end = body.find("$$")?;
+        let tex = body[..end].trim();
+        return if tex.is_empty() {
+            None
+        } else {
+            Some((tex, start + end + 4, true))
+        };
+    }
+
+    let body = &rest[1..];
+    if body.is_empty() || body.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let bytes = body.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'\n'
+                if body[index + 1..]
+                    .trim_start_matches([' ', '\t'])
+                    .starts_with('\n') =>
+            {
+                return None
+            }
+            b'$' => {
+                let is_before_digit = matches!(bytes.get(index + 1), Some(b) if b.is_ascii_digit());
+                if bytes[index - 1].is_ascii_whitespace() || is_before_digit {
+                    index += 1;
+                } else {
+                    return Some((&body[..index], start + index + 2, false));
+                }
+            }
+            _ => index += 1,
+        }
+    }
+    None
+}
+
+fn render(tex: &str, display: bool) -> String {
+    let tex = tera::escape_html(tex);
+    if display {
+        format!(r#"<span class="math display">\[{}\]</span>"#, tex)
+    } else {
+        format!(r#"<span class="math inline">\({}\)</span>"#, tex)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::Markdown,
+        data::{MarkdownContent, MarkdownRenderer},
+    };
+
+    fn render(raw: &str) -> MarkdownContent {
+        let config = Markdown {
+            extensions: vec!["ruby".to_string(), "math".to_string()],
+            ..Default::default()
+        };
+        let renderer = MarkdownRenderer::new(config).unwrap();
+        MarkdownContent::render(raw.to_string(), &renderer).unwrap()
+    }
+
+    #[test]
+    fn should_keep_math_out_of_markdown() {
+        assert_eq!(
+            "<p>let <span class=\"math inline\">\\(a_1 * b_2 &lt; {c}(d)\\)</span> be</p>\n",
+            render("let $a_1 * b_2 < {c}(d)$ be").html
+        );
+        assert_eq!(
+            "<span class=\"math display\">\\[\\sum_{i=1}^n *i*\\]</span>",
+            render("$$\n\\sum_{i=1}^n *i*\n$$\n").html
+        );
+    }
+
+    #[test]
+    fn should_not_treat_dollars_as_math() {
+        for raw in &[
+            "costs $5 and $10",
+            "\\$a$ escaped",
+            "`$a$` in code",
+            "$ a$ with space",
+            "$a\n\nb$",
+        ] {
+            assert_eq!(MarkdownContent::new(raw.to_string()).html, render(raw).html);
+        }
+    }
+
+    #[test]
+    fn should_use_tex_as_heading_title() {
+        let content = render("## Time $O(n)$");
+        assert_eq!("Time O(n)", content.toc[0].title);
+        assert_eq!("time-on", content.toc[0].id);
+    }
+}
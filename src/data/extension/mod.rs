@@ -0,0 +1,128 @@
+use crate::{
+    data::{
+        extension::{math::Math, ruby::Ruby},
+        placeholder::Placeholders,
+    },
+    error::StapleError,
+};
+use pulldown_cmark::{Event, Options};
+use std::{borrow::Cow, fmt::Debug};
+
+pub(crate) mod math;
+pub(crate) mod ruby;
+
+/// transformation of markdown events, enabled by `extensions` in `[markdown]` section
+pub trait MarkdownExtension: Debug + Send + Sync {
+    /// name used in config
+    fn name(&self) -> &'static str;
+
+    /// whether enabled when `extensions` is not configured
+    fn is_default(&self) -> bool {
+        true
+    }
+
+    /// rewrite raw markdown before parsing, syntax that markdown should not touch can be
+    /// replaced by placeholder of its html
+    fn preprocess(
+        &self,
+        raw: String,
+        _options: Options,
+        _placeholders: &mut Placeholders,
+    ) -> String {
+        raw
+    }
+
+    fn process<'a>(&self, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+        events
+    }
+
+    /// text without extension syntax, used as title of heading
+    fn plain_text<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        Cow::Borrowed(text)
+    }
+}
+
+pub type Extensions = Vec<Box<dyn MarkdownExtension>>;
+
+/// all known extensions, site picks enabled ones by name
+#[derive(Debug, Default)]
+pub struct Registry {
+    extensions: Extensions,
+}
+
+impl Registry {
+    pub fn builtin() -> Self {
+        let mut registry = Registry::default();
+        registry.register(Box::new(Ruby));
+        registry.register(Box::new(Math));
+        registry
+    }
+
+    pub fn register(&mut self, extension: Box<dyn MarkdownExtension>) {
+        self.extensions.push(extension);
+    }
+
+    #[cfg(test)]
+    pub fn names(&self) -> Vec<&'static str> {
+        self.extensions.iter().map(|e| e.name()).collect()
+    }
+
+    pub fn default_names(&self) -> Vec<&'static str> {
+        self.extensions
+            .iter()
+            .filter(|e| e.is_default())
+            .map(|e| e.name())
+            .collect()
+    }
+
+    /// extensions of given names in the same order
+    pub fn select(mut self, names: &[String]) -> Result<Extensions, StapleError> {
+        let mut selected = vec![];
+        for name in names {
+            match self.extensions.iter().position(|e| e.name() == name) {
+                Some(index) => selected.push(self.extensions.remove(index)),
+                None => return Err(StapleError::ExtensionNotFound(name.clone())),
+            }
+        }
+        Ok(selected)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::Markdown,
+        data::{extension::Registry, MarkdownContent, MarkdownRenderer},
+        error::StapleError,
+    };
+
+    #[test]
+    fn should_select_extensions_by_name() {
+        assert_eq!(vec!["ruby", "math"], Registry::builtin().names());
+        assert_eq!(vec!["ruby"], Registry::builtin().default_names());
+        let selected = Registry::builtin().select(&[]).unwrap();
+        assert!(selected.is_empty());
+        assert!(matches!(
+            Registry::builtin().select(&["nope".to_string()]),
+            Err(StapleError::ExtensionNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn should_render_without_disabled_features() {
+        let config = Markdown {
+            extensions: vec![],
+            strikethrough: false,
+            ..Default::default()
+        };
+        let renderer = MarkdownRenderer::new(config).unwrap();
+        let content = MarkdownContent::render("{a}(b) ~~c~~".to_string(), &renderer).unwrap();
+        assert_eq!("<p>{a}(b) ~~c~~</p>\n", content.html);
+
+        let content = MarkdownContent::new("{a}(b) ~~c~~".to_string());
+        assert_eq!(
+            "<p><ruby>a<rp>(</rp><rt>b</rt><rp>)</rp></ruby> <del>c</del></p>\n",
+            content.html
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use crate::data::extension::MarkdownExtension;
+use once_cell::sync::Lazy;
+use pulldown_cmark::Event;
+use regex::Regex;
+use std::borrow::Cow;
+
+static RUBY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\{(?P<title>[^}]+)}\((?P<ruby>[^)]+)\)"#).expect("invalid regex"));
+
+/// ruby annotation `{title}(ruby)` rendered as `<ruby>` tag
+#[derive(Debug)]
+pub struct Ruby;
+
+impl MarkdownExtension for Ruby {
+    fn name(&self) -> &'static str {
+        "ruby"
+    }
+
+    fn process<'a>(&self, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+        events
+            .into_iter()
+            .flat_map(|event| match event {
+                Event::Text(text) if RUBY_REGEX.is_match(&text) => {
+                    let mut events = vec![];
+                    let mut last_end_index = 0;
+                    for captures in RUBY_REGEX.captures_iter(&text) {
+                        let ruby_group = captures.get(0).expect("invalid ruby group");
+                        let ruby_name = captures.name("title").expect("invalid title").as_str();
+                        let ruby_description =
+                            captures.name("ruby").expect("invalid ruby").as_str();
+
+                        if last_end_index != ruby_group.start() {
+                            let prefix = &text[last_end_index..ruby_group.start()];
+                            events.push(Event::Text(prefix.to_string().into()));
+                        }
+                        last_end_index = ruby_group.end();
+
+                        events.push(Event::Html("<ruby>".into()));
+                        events.push(Event::Text(ruby_name.to_string().into()));
+                        events.push(Event::Html("<rp>(</rp><rt>".into()));
+                        events.push(Event::Text(ruby_description.to_string().into()));
+                        events.push(Event::Html("</rt><rp>)</rp>".into()));
+                        events.push(Event::Html("</ruby>".into()));
+                    }
+                    if last_end_index < text.len() {
+                        events.push(Event::Text(text[last_end_index..].to_string().into()));
+                    }
+                    events
+                }
+                _ => vec![event],
+            })
+            .collect()
+    }
+
+    /// keep only the base text
+    fn plain_text<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        RUBY_REGEX.replace_all(text, "$title")
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    config::{Highlight, HighlightStyle},
+    error::StapleError,
+};
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use pulldown_cmark::{CowStr, Event, Tag};
+use std::collections::HashSet;
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Color, Theme, ThemeSet},
+    html::{
+        append_highlighted_html_for_styled_line, css_for_theme_with_class_style,
+        line_tokens_to_classed_spans, ClassStyle, IncludeBackground,
+    },
+    parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEME_SET: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
+
+const CLASS_PREFIX: &str = "hl-";
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed {
+    prefix: CLASS_PREFIX,
+};
+
+pub fn get_theme(name: &str) -> Result<&'static Theme, StapleError> {
+    THEME_SET
+        .themes
+        .get(name)
+        .ok_or_else(|| StapleError::HighlightThemeNotFound(name.to_string()))
+}
+
+pub fn theme_names() -> Vec<&'static str> {
+    THEME_SET.themes.keys().map(String::as_str).collect()
+}
+
+/// stylesheet for `class` style highlighting, including line number and highlighted line
+pub fn theme_css(name: &str) -> Result<String, StapleError> {
+    let theme = get_theme(name)?;
+    let mut css =
+        css_for_theme_with_class_style(theme, CLASS_STYLE).expect("cannot generate css for theme");
+    css.push_str(
+        ".highlight .line-number { user-select: none; opacity: 0.5; margin-right: 1em; }\n",
+    );
+    if let Some(color) = theme.settings.line_highlight {
+        css.push_str(&format!(
+            ".highlight .hl-line {{ background-color: {}; color: inherit; }}\n",
+            css_color(color)
+        ));
+    }
+    Ok(css)
+}
+
+/// attributes of fenced code block, e.g. ```` ```rust,linenos,hl_lines=1 3-5 ````
+#[derive(Debug, Default, PartialEq)]
+pub struct FenceInfo {
+    pub language: Option<String>,
+    pub line_numbers: bool,
+    pub highlighted_lines: HashSet<usize>,
+}
+
+impl FenceInfo {
+    pub fn parse(info: &str) -> Self {
+        let mut fence = FenceInfo::default();
+        let mut attributes = info.split(',').map(str::trim);
+        fence.language = attributes
+            .next()
+            .filter(|language| !language.is_empty())
+            .map(str::to_string);
+        for attribute in attributes {
+            match attribute.splitn(2, '=').collect_tuple() {
+                Some(("hl_lines", lines)) => {
+                    for range in lines.split_whitespace() {
+                        let (start, end) = match range.splitn(2, '-').collect_tuple() {
+                            Some((start, end)) => (start.parse::<usize>(), end.parse::<usize>()),
+                            None => (range.parse(), range.parse()),
+                        };
+                        if let (Ok(start), Ok(end)) = (start, end) {
+                            fence.highlighted_lines.extend(start..=end);
+                        }
+                    }
+                }
+                None if attribute == "linenos" => fence.line_numbers = true,
+                _ => {}
+            }
+        }
+        fence
+    }
+}
+
+/// replace fenced code blocks with highlighted html
+pub fn highlight_code_blocks<'a>(
+    events: impl Iterator<Item = Event<'a>>,
+    config: &Highlight,
+) -> Vec<Event<'a>> {
+    let mut output = vec![];
+    let mut block: Option<(CowStr<'a>, String)> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(info)) => block = Some((info, String::new())),
+            Event::End(Tag::CodeBlock(info)) => {
+                let code = block.take().map(|(_, code)| code).unwrap_or_default();
+                output.push(Event::Html(highlight(&info, &code, config).into()));
+            }
+            Event::Text(text) if block.is_some() => {
+                if let Some((_, code)) = block.as_mut() {
+                    code.push_str(&text);
+                }
+            }
+            other => output.push(other),
+        }
+    }
+    output
+}
+
+pub fn highlight(info: &str, code: &str, config: &Highlight) -> String {
+    let fence = FenceInfo::parse(info);
+    let syntax = fence
+        .language
+        .as_deref()
+        .and_then(|language| SYNTAX_SET.find_syntax_by_token(language))
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+    let theme = get_theme(&config.theme).unwrap_or_else(|_| &THEME_SET.themes["InspiredGitHub"]);
+
+    let lines = match config.style {
+        HighlightStyle::Inline => highlight_inline(code, syntax, theme),
+        HighlightStyle::Class => highlight_classed(code, syntax),
+    }
+    .unwrap_or_else(|e| {
+        warn!("fail to highlight code block: {}", e);
+        LinesWithEndings::from(code)
+            .map(tera::escape_html)
+            .collect()
+    });
+
+    let mut html = String::new();
+    let language = fence.language.as_deref().map(tera::escape_html);
+    match (config.style, theme.settings.background) {
+        (HighlightStyle::Inline, Some(background)) => html.push_str(&format!(
+            r#"<pre class="highlight" style="background-color:{};""#,
+            css_color(background)
+        )),
+        (HighlightStyle::Inline, None) => html.push_str(r#"<pre class="highlight""#),
+        (HighlightStyle::Class, _) => {
+            html.push_str(&format!(r#"<pre class="highlight {}code""#, CLASS_PREFIX))
+        }
+    }
+    match &language {
+        Some(language) => html.push_str(&format!(
+            r#" data-lang="{0}"><code class="language-{0}">"#,
+            language
+        )),
+        None => html.push_str("><code>"),
+    }
+    for (index, line) in lines.iter().enumerate() {
+        let number = index + 1;
+        let is_highlighted = fence.highlighted_lines.contains(&number);
+        if is_highlighted {
+            match (config.style, theme.settings.line_highlight) {
+                (HighlightStyle::Inline, Some(color)) => html.push_str(&format!(
+                    r#"<mark class="hl-line" style="background-color:{};color:inherit;">"#,
+                    css_color(color)
+                )),
+                _ => html.push_str(r#"<mark class="hl-line">"#),
+            }
+        }
+        if fence.line_numbers {
+            match config.style {
+                HighlightStyle::Inline => html.push_str(&format!(
+                    r#"<span class="line-number" style="user-select:none;opacity:0.5;margin-right:1em;">{}</span>"#,
+                    number
+                )),
+                HighlightStyle::Class => {
+                    html.push_str(&format!(r#"<span class="line-number">{}</span>"#, number))
+                }
+            }
+        }
+        html.push_str(line);
+        if is_highlighted {
+            html.push_str("</mark>");
+        }
+    }
+    html.push_str("</code></pre>\n");
+    html
+}
+
+fn highlight_inline(
+    code: &str,
+    syntax: &SyntaxReference,
+    theme: &Theme,
+) -> Result<Vec<String>, syntect::Error> {
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut lines = vec![];
+    for line in LinesWithEndings::from(code) {
+        let regions = highlighter.highlight_line(line, &SYNTAX_SET)?;
+        let mut html = String::new();
+        append_highlighted_html_for_styled_line(&regions, IncludeBackground::No, &mut html)?;
+        lines.push(html);
+    }
+    Ok(lines)
+}
+
+/// every line is self-contained, spans opened in previous lines are reopened and those left open
+/// are closed at the end of line, so that a line can be wrapped by line number and mark.
+fn highlight_classed(code: &str, syntax: &SyntaxReference) -> Result<Vec<String>, syntect::Error> {
+    let mut parse_state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut lines = vec![];
+    for line in LinesWithEndings::from(code) {
+        let mut html: String = stack
+            .as_slice()
+            .iter()
+            .map(|scope| format!(r#"<span class="{}">"#, scope_classes(*scope)))
+            .collect();
+        let ops = parse_state.parse_line(line, &SYNTAX_SET)?;
+        let (spans, _) = line_tokens_to_classed_spans(line, &ops, CLASS_STYLE, &mut stack)?;
+        html.push_str(&spans);
+        html.push_str(&"</span>".repeat(stack.len()));
+        lines.push(html);
+    }
+    Ok(lines)
+}
+
+fn scope_classes(scope: Scope) -> String {
+    scope
+        .build_string()
+        .split('.')
+        .map(|atom| format!("{}{}", CLASS_PREFIX, atom))
+        .join(" ")
+}
+
+fn css_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::{Highlight, HighlightStyle},
+        data::highlight::{highlight, theme_css, FenceInfo},
+    };
+
+    #[test]
+    fn should_parse_fence_info() {
+        let fence = FenceInfo::parse("rust,linenos,hl_lines=1 3-4");
+        assert_eq!(Some("rust".to_string()), fence.language);
+        assert!(fence.line_numbers);
+        let mut lines: Vec<usize> = fence.highlighted_lines.into_iter().collect();
+        lines.sort();
+        assert_eq!(vec![1, 3, 4], lines);
+
+        assert_eq!(FenceInfo::default(), FenceInfo::parse(""));
+    }
+
+    #[test]
+    fn should_highlight_with_inline_style() {
+        let config = Highlight::default();
+        let html = highlight("rust", "fn main() {}\n", &config);
+        assert!(html.starts_with(r#"<pre class="highlight" style="background-color:#ffffff;" data-lang="rust"><code class="language-rust">"#));
+        assert!(html.contains(r#"<span style="color:"#));
+        assert!(html.ends_with("</code></pre>\n"));
+    }
+
+    #[test]
+    fn should_highlight_with_class_and_close_spans_per_line() {
+        let config = Highlight {
+            style: HighlightStyle::Class,
+            ..Default::default()
+        };
+        let html = highlight("rust,linenos,hl_lines=2", "fn main() {\n}\n", &config);
+        assert!(html.contains(r#"<span class="line-number">1</span>"#));
+        assert!(html.contains(r#"<mark class="hl-line"><span class="line-number">2</span>"#));
+        assert!(html.contains("hl-source hl-rust"));
+        assert_eq!(
+            html.matches("<span").count(),
+            html.matches("</span>").count()
+        );
+    }
+
+    #[test]
+    fn should_escape_unknown_language() {
+        let config = Highlight::default();
+        let html = highlight("not-a-lang", "<a>\n", &config);
+        assert!(html.contains("&lt;a&gt;"));
+    }
+
+    #[test]
+    fn should_export_theme_css() {
+        assert!(theme_css("InspiredGitHub").unwrap().contains(".hl-code"));
+        assert!(theme_css("not-exist").is_err());
+    }
+}
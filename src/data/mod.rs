@@ -1,86 +1,169 @@
 use chrono::{DateTime, FixedOffset};
-use itertools::Itertools;
-use pulldown_cmark::{Event, Options};
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use std::{collections::HashMap, path::Path};
 
 use crate::{
-    data::types::{json::JsonFileData, markdown::MarkdownFileData, FileType},
+    config::{Config, Markdown, OutputFormat},
+    data::{
+        bundle::{Bundle, Bundles},
+        diagram::DiagramOutputs,
+        extension::{Extensions, Registry},
+        placeholder::Placeholders,
+        section::{SectionDefaults, Sections},
+        shortcode::{Shortcodes, SHORTCODES_FOLDER},
+        toc::TocItem,
+        types::{json::JsonFileData, markdown::MarkdownFileData, FileType},
+    },
     error::StapleError,
+    images::{self, ImageProcessor},
 };
 
+pub(crate) mod bundle;
+pub(crate) mod diagram;
+pub(crate) mod extension;
+pub(crate) mod highlight;
+pub(crate) mod placeholder;
+pub(crate) mod section;
+pub(crate) mod shortcode;
+pub(crate) mod toc;
 pub(crate) mod types;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct MarkdownContent {
     pub markdown: String,
     pub html: String,
+    #[serde(default)]
+    pub toc: Vec<TocItem>,
+}
+
+/// markdown config of site along with enabled extensions, shortcodes of theme, image processor
+/// and page bundles of data folder
+#[derive(Debug)]
+pub struct MarkdownRenderer {
+    pub config: Markdown,
+    pub extensions: Extensions,
+    pub shortcodes: Option<Shortcodes>,
+    pub images: Option<ImageProcessor>,
+    pub bundles: Option<Bundles>,
+    pub sections: Option<Sections>,
+    pub diagrams: DiagramOutputs,
+}
+
+impl Default for MarkdownRenderer {
+    fn default() -> Self {
+        MarkdownRenderer::new(Markdown::default()).expect("invalid builtin extensions")
+    }
+}
+
+impl MarkdownRenderer {
+    pub fn new(config: Markdown) -> Result<Self, StapleError> {
+        let extensions = Registry::builtin().select(&config.extensions)?;
+        Ok(MarkdownRenderer {
+            config,
+            extensions,
+            shortcodes: None,
+            images: None,
+            bundles: None,
+            sections: None,
+            diagrams: DiagramOutputs::default(),
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>, config: &Config) -> Result<Self, StapleError> {
+        let folder = path
+            .as_ref()
+            .join("templates")
+            .join(config.get_theme()?)
+            .join(SHORTCODES_FOLDER);
+        Ok(MarkdownRenderer {
+            shortcodes: Shortcodes::load(folder)?,
+            images: Some(ImageProcessor::new(&path, config)),
+            bundles: Some(Bundles::new(&path, config)),
+            sections: Some(Sections::new(&path)),
+            ..MarkdownRenderer::new(config.markdown.clone())?
+        })
+    }
+
+    /// page bundle of data file generated at `url`
+    pub fn bundle(&self, file: impl AsRef<Path>, url: &str) -> Option<Bundle> {
+        self.bundles.as_ref()?.find(file, url)
+    }
+
+    /// defaults of data file given by sections containing it
+    pub fn section_defaults(&self, file: impl AsRef<Path>) -> Result<SectionDefaults, StapleError> {
+        match &self.sections {
+            Some(sections) => sections.defaults(file),
+            None => Ok(SectionDefaults::default()),
+        }
+    }
+
+    /// text without syntax of enabled extensions
+    pub fn plain_text(&self, text: &str) -> String {
+        self.extensions
+            .iter()
+            .fold(text.to_string(), |text, extension| {
+                extension.plain_text(&text).into_owned()
+            })
+    }
 }
 
 impl MarkdownContent {
+    #[cfg(test)]
     pub fn new(raw: String) -> Self {
+        MarkdownContent::render(raw, &MarkdownRenderer::default())
+            .expect("markdown without shortcodes cannot fail")
+    }
+
+    pub fn render(raw: String, renderer: &MarkdownRenderer) -> Result<Self, StapleError> {
+        MarkdownContent::render_page(raw, renderer, None)
+    }
+
+    /// render markdown of page, relative links to assets of its bundle are pointed to their output
+    pub fn render_page(
+        raw: String,
+        renderer: &MarkdownRenderer,
+        bundle: Option<&Bundle>,
+    ) -> Result<Self, StapleError> {
+        let config = &renderer.config;
+        let mut placeholders = Placeholders::default();
+        let mut source = match &renderer.shortcodes {
+            Some(shortcodes) => shortcodes.expand(&raw, renderer, &mut placeholders)?,
+            None => raw.clone(),
+        };
+        for extension in &renderer.extensions {
+            source = extension.preprocess(source, config.options(), &mut placeholders);
+        }
         let mut html_output = String::new();
-        let options = Options::all();
-        let parser = pulldown_cmark::Parser::new_ext(&raw, options).flat_map(|event| match event {
-            Event::Text(text) => {
-                let mut text_chars = text.as_bytes().iter();
-                let mut events = vec![];
-                let re =
-                    Regex::new(r#"\{(?P<title>[^}]+)}\((?P<ruby>[^)]+)\)"#).expect("invalid regex");
-                let mut last_end_index = 0;
-                for captures in re.captures_iter(&text) {
-                    let ruby_group = captures.get(0).unwrap();
-                    let ruby_name = captures
-                        .name("title")
-                        .expect("invalid title")
-                        .as_str()
-                        .to_string();
-                    let ruby_description = captures
-                        .name("ruby")
-                        .expect("invalid ruby")
-                        .as_str()
-                        .to_string();
-                    let ruby_group_start = ruby_group.start();
-
-                    if last_end_index != ruby_group_start {
-                        let ruby_prefix_content: Vec<u8> = text_chars
-                            .by_ref()
-                            .take(ruby_group_start - last_end_index)
-                            .copied()
-                            .collect();
-                        let string =
-                            String::from_utf8(ruby_prefix_content).expect("invalid utf8 string");
-                        events.push(Event::Text(string.into()));
-                    }
-                    last_end_index = ruby_group.end();
-                    text_chars = text_chars.dropping(ruby_group.end() - ruby_group.start());
-
-                    events.push(Event::Html("<ruby>".into()));
-                    events.push(Event::Text(ruby_name.into()));
-                    events.push(Event::Html("<rp>(</rp><rt>".into()));
-                    events.push(Event::Text(ruby_description.into()));
-                    events.push(Event::Html("</rt><rp>)</rp>".into()));
-                    events.push(Event::Html("</ruby>".into()));
-                }
-                if last_end_index < text.len() {
-                    let rest: Vec<u8> = text_chars.copied().collect();
-                    let rest = String::from_utf8(rest).expect("invalid utf8 string");
-                    events.push(Event::Text(rest.into()));
-                }
-                events
+        let parser =
+            pulldown_cmark::Parser::new_ext(&source, config.options()).map(|event| match bundle {
+                Some(bundle) => bundle.rewrite_link(event),
+                None => event,
+            });
+        let events = diagram::render_diagrams(parser, &config.diagrams, &renderer.diagrams)?;
+        let events = match (&config.images, &renderer.images) {
+            (Some(images), Some(processor)) => {
+                images::responsive_images(events.into_iter(), processor, images, bundle)?
             }
+            _ => events,
+        };
+        let events = match &config.highlight {
+            Some(highlight) => highlight::highlight_code_blocks(events.into_iter(), highlight),
+            None => events,
+        };
+        let (mut events, toc) = toc::process_headings(events.into_iter(), renderer, &placeholders);
+        for extension in &renderer.extensions {
+            events = extension.process(events);
+        }
+        pulldown_cmark::html::push_html(&mut html_output, events.into_iter());
+        let html_output = placeholders.restore(html_output);
 
-            _ => vec![event],
-        });
-        pulldown_cmark::html::push_html(&mut html_output, parser);
-
-        Self {
+        Ok(Self {
             markdown: raw,
             html: html_output,
-        }
+            toc,
+        })
     }
 }
 
@@ -92,21 +175,23 @@ pub enum DataFile {
 }
 
 impl DataFile {
-    pub fn template(&self) -> &str {
+    pub fn content(&self) -> &MarkdownContent {
         match &self {
-            DataFile::JsonFile(data) => &data.template,
-            DataFile::MarkdownFile(data) => &data.template,
+            DataFile::JsonFile(data) => &data.content,
+            DataFile::MarkdownFile(data) => &data.content,
         }
     }
 
-    pub fn load(path: impl AsRef<Path>) -> Result<Option<PageInfo>, StapleError> {
+    pub fn load(
+        path: impl AsRef<Path>,
+        renderer: &MarkdownRenderer,
+    ) -> Result<Option<PageInfo>, StapleError> {
         let extension = path.as_ref().extension().and_then(|e| e.to_str());
         match extension {
-            Some("md") => {
-                MarkdownFileData::load(path.as_ref()).map(|full| Some(full.into_page_info()))
-            }
+            Some("md") => MarkdownFileData::load(path.as_ref(), renderer)
+                .map(|full| Some(full.into_page_info())),
             Some("json") => {
-                JsonFileData::load(path.as_ref()).map(|full| Some(full.into_page_info()))
+                JsonFileData::load(path.as_ref(), renderer).map(|full| Some(full.into_page_info()))
             }
             _ => Ok(None),
         }
@@ -127,45 +212,103 @@ pub struct PageInfo {
 }
 
 impl PageInfo {
-    pub fn to_full_article(&self) -> Result<DataFile, StapleError> {
+    pub fn to_full_article(&self, renderer: &MarkdownRenderer) -> Result<DataFile, StapleError> {
         let path = Path::new(&self.file);
         let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
         match extension {
-            "md" => MarkdownFileData::load(path.to_str().expect("invalid file path encoding"))
-                .map(DataFile::MarkdownFile),
+            "md" => {
+                MarkdownFileData::load(path.to_str().expect("invalid file path encoding"), renderer)
+                    .map(DataFile::MarkdownFile)
+            }
 
-            "json" => JsonFileData::load(path).map(DataFile::JsonFile),
+            "json" => JsonFileData::load(path, renderer).map(DataFile::JsonFile),
             _ => unreachable!(),
         }
     }
+    /// terms of taxonomy in page's data, either an array or a comma separated string
+    pub fn terms(&self, taxonomy: &str) -> Vec<String> {
+        match self.data.get(taxonomy) {
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(|value| match value {
+                    Value::String(term) => term.trim().to_string(),
+                    other => other.to_string(),
+                })
+                .filter(|term| !term.is_empty())
+                .collect(),
+            Some(Value::String(terms)) => terms
+                .split(',')
+                .map(str::trim)
+                .filter(|term| !term.is_empty())
+                .map(str::to_string)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// extra output formats of page, formats in its `outputs` field take precedence over those
+    /// of its section in `[[outputs]]`
+    pub fn output_formats<'a>(
+        &self,
+        config: &'a Config,
+    ) -> Result<Vec<&'a OutputFormat>, StapleError> {
+        let names = if self.data.contains_key("outputs") {
+            self.terms("outputs")
+        } else {
+            config
+                .outputs
+                .iter()
+                .filter(|outputs| outputs.contains(&self.url))
+                .max_by_key(|outputs| outputs.section.trim_matches('/').len())
+                .map(|outputs| outputs.formats.clone())
+                .unwrap_or_default()
+        };
+        names
+            .into_iter()
+            .map(|name| {
+                config
+                    .output_formats
+                    .get(&name)
+                    .ok_or(StapleError::OutputFormatNotFound(name))
+            })
+            .collect()
+    }
+
     ///
     ///
     pub fn output_file_name(&self) -> String {
-        let url = Path::new(&self.url);
-        let has_extension = url.extension().is_some();
-        let start_with_slash = self.url.starts_with('/');
-        let end_with_slash = self.url.ends_with('/');
-
-        format!(
-            "{}{}",
-            if start_with_slash {
-                &self.url[1..self.url.len()]
-            } else {
-                &self.url
-            },
-            if has_extension {
-                ""
-            } else if end_with_slash {
-                "index.html"
-            } else {
-                "/index.html"
-            }
-        )
+        output_file_name(&self.url)
     }
 }
+
+/// file path relative to output folder of url
+pub fn output_file_name(url: &str) -> String {
+    let has_extension = Path::new(url).extension().is_some();
+    let start_with_slash = url.starts_with('/');
+    let end_with_slash = url.ends_with('/');
+
+    format!(
+        "{}{}",
+        if start_with_slash {
+            &url[1..url.len()]
+        } else {
+            url
+        },
+        if has_extension {
+            ""
+        } else if end_with_slash {
+            "index.html"
+        } else {
+            "/index.html"
+        }
+    )
+}
 #[cfg(test)]
 mod test {
-    use crate::data::{MarkdownContent, PageInfo};
+    use crate::{
+        config::Markdown,
+        data::{MarkdownContent, MarkdownRenderer, PageInfo},
+    };
     use chrono::{FixedOffset, Utc};
 
     #[test]
@@ -186,6 +329,27 @@ mod test {
         );
     }
 
+    #[test]
+    fn should_highlight_code_block_when_configured() {
+        let config = Markdown {
+            highlight: Some(Default::default()),
+            ..Default::default()
+        };
+        let content = MarkdownContent::render(
+            "```rust\nlet a = \"{ruby}(desc)\";\n```\n".to_string(),
+            &MarkdownRenderer::new(config).expect("invalid config"),
+        )
+        .expect("cannot render markdown");
+        assert!(content.html.starts_with(r#"<pre class="highlight""#));
+        assert!(!content.html.contains("<ruby>"));
+
+        let content = MarkdownContent::new("```rust\nlet a = 1;\n```\n".to_string());
+        assert_eq!(
+            "<pre><code class=\"language-rust\">let a = 1;\n</code></pre>\n",
+            content.html
+        );
+    }
+
     #[test]
     fn test_page_info_output_file_name() {
         fn get_page_info_output_file_name(url: &str) -> String {
@@ -0,0 +1,55 @@
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use std::ops::Range;
+
+/// html kept away from markdown parser, its placeholder in markdown is put back after rendering
+#[derive(Debug, Default)]
+pub struct Placeholders {
+    items: Vec<(String, String)>,
+}
+
+impl Placeholders {
+    /// placeholder of html, `plain` is used where only text is allowed, like title of heading
+    pub fn insert(&mut self, html: String, plain: String) -> String {
+        self.items.push((html, plain));
+        placeholder(self.items.len() - 1)
+    }
+
+    /// put html back, paragraph wrapping a standalone placeholder is removed
+    pub fn restore(&self, mut html: String) -> String {
+        for (index, (output, _)) in self.items.iter().enumerate() {
+            let placeholder = placeholder(index);
+            html = html
+                .replace(&format!("<p>{}</p>\n", placeholder), output)
+                .replace(&placeholder, output);
+        }
+        html
+    }
+
+    pub fn plain_text(&self, text: &str) -> String {
+        self.items
+            .iter()
+            .enumerate()
+            .fold(text.to_string(), |text, (index, (_, plain))| {
+                text.replace(&placeholder(index), plain)
+            })
+    }
+}
+
+fn placeholder(index: usize) -> String {
+    format!("@@placeholder-{}@@", index)
+}
+
+/// range of code blocks and inline codes, whose content should be kept as it is
+pub fn code_ranges(raw: &str, options: Options) -> Vec<Range<usize>> {
+    Parser::new_ext(raw, options)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn in_code(code: &[Range<usize>], position: usize) -> bool {
+    code.iter().any(|range| range.contains(&position))
+}
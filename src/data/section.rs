@@ -0,0 +1,343 @@
+use crate::{
+    data::{bundle::is_bundle_folder, types::markdown, PageInfo},
+    error::StapleError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// file stem of section's index page, like `data/post/_index.md`
+pub const SECTION_INDEX: &str = "_index";
+
+/// whether file is index page of a section
+pub fn is_section_index(file: impl AsRef<Path>) -> bool {
+    file.as_ref().file_stem().and_then(|stem| stem.to_str()) == Some(SECTION_INDEX)
+}
+
+/// defaults of pages in section, set in data of its index page. those of the nearest section
+/// take precedence over outer ones.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SectionDefaults {
+    /// template of pages without their own
+    pub page_template: Option<String>,
+    /// prefix of pages' urls not starting with `/`, like `post` for `/post/hello` of `hello`
+    pub url_prefix: Option<String>,
+    /// order of `section.pages`, `datetime` from the newest by default, `title` or `weight`
+    pub sort_by: Option<SortBy>,
+    /// whether pages without `draw` field are drafts
+    pub page_draft: Option<bool>,
+}
+
+impl SectionDefaults {
+    pub fn from_data(data: HashMap<String, Value>) -> Result<Self, String> {
+        serde_json::from_value(Value::Object(data.into_iter().collect()))
+            .map_err(|e| format!("invalid section defaults: {}", e))
+    }
+
+    /// url of page with url prefix applied
+    pub fn url(&self, url: String) -> String {
+        match &self.url_prefix {
+            Some(prefix) if !url.starts_with('/') => {
+                let prefix = prefix.trim_matches('/');
+                if prefix.is_empty() {
+                    format!("/{}", url)
+                } else {
+                    format!("/{}/{}", prefix, url)
+                }
+            }
+            _ => url,
+        }
+    }
+
+    /// fill settings absent here with those of outer section
+    fn or(self, outer: SectionDefaults) -> SectionDefaults {
+        SectionDefaults {
+            page_template: self.page_template.or(outer.page_template),
+            url_prefix: self.url_prefix.or(outer.url_prefix),
+            sort_by: self.sort_by.or(outer.sort_by),
+            page_draft: self.page_draft.or(outer.page_draft),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    Datetime,
+    Title,
+    Weight,
+}
+
+impl SortBy {
+    fn compare(self, one: &PageInfo, other: &PageInfo) -> Ordering {
+        match self {
+            SortBy::Datetime => other.datetime.cmp(&one.datetime),
+            SortBy::Title => one.title.cmp(&other.title),
+            SortBy::Weight => {
+                let weight = |page: &PageInfo| page.data.get("weight").and_then(Value::as_f64);
+                match (weight(one), weight(other)) {
+                    (Some(one), Some(other)) => one.partial_cmp(&other).unwrap_or(Ordering::Equal),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            }
+        }
+    }
+}
+
+/// recognizes folders in data folder as sections, like `data/post` with optional
+/// `data/post/_index.md`. folders of page bundles are part of their outer section.
+#[derive(Debug)]
+pub struct Sections {
+    data_folder: PathBuf,
+    /// defaults set by each index page, or why they cannot be read
+    defaults: HashMap<PathBuf, Result<SectionDefaults, String>>,
+}
+
+impl Sections {
+    /// read defaults of all index pages in data folder, invalid ones are reported once pages
+    /// of their sections are loaded
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let data_folder = path.as_ref().join("data");
+        let defaults = WalkDir::new(&data_folder)
+            .into_iter()
+            .flat_map(|e| e.ok())
+            .filter(|de| de.path().is_file() && is_section_index(de.path()))
+            .map(|de| {
+                let defaults = read_data(de.path())
+                    .map_err(|e| e.to_string())
+                    .and_then(SectionDefaults::from_data);
+                (de.into_path(), defaults)
+            })
+            .collect();
+        Sections {
+            data_folder,
+            defaults,
+        }
+    }
+
+    /// defaults of data file from index pages of sections containing it
+    pub fn defaults(&self, file: impl AsRef<Path>) -> Result<SectionDefaults, StapleError> {
+        let file = file.as_ref();
+        let mut defaults = SectionDefaults::default();
+        let folders = file
+            .ancestors()
+            .skip(1)
+            .take_while(|folder| folder.starts_with(&self.data_folder));
+        for folder in folders {
+            for extension in &["md", "json"] {
+                let index = folder.join(format!("{}.{}", SECTION_INDEX, extension));
+                let section = match self.defaults.get(&index) {
+                    Some(Ok(section)) => section,
+                    Some(Err(reason)) => {
+                        return Err(StapleError::ArticleError {
+                            filename: index.to_string_lossy().to_string(),
+                            reason: reason.clone(),
+                        })
+                    }
+                    None => continue,
+                };
+                // index page is checked above but its own defaults are for other pages
+                if index != file {
+                    defaults = defaults.or(section.clone());
+                }
+            }
+        }
+        Ok(defaults)
+    }
+
+    /// path of section relative to data folder like `post/2020`, empty for data folder itself
+    pub fn path_of(&self, file: impl AsRef<Path>) -> Option<String> {
+        let file = file.as_ref();
+        let mut folder = file.parent()?;
+        if !is_section_index(file) && folder != self.data_folder && is_bundle_folder(folder) {
+            folder = folder.parent()?;
+        }
+        let relative = folder.strip_prefix(&self.data_folder).ok()?;
+        Some(
+            relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+
+    /// tree of sections from data folder
+    pub fn build<'a>(&self, pages: &'a [PageInfo]) -> Section<'a> {
+        let mut root = Section::default();
+        for page in pages {
+            if let Some(path) = self.path_of(&page.file) {
+                let section = root.entry(&path);
+                if is_section_index(&page.file) {
+                    section.index = Some(page);
+                } else {
+                    section.pages.push(page);
+                }
+            }
+        }
+        root.sort(self, None);
+        root
+    }
+}
+
+/// section with its pages and subsections
+#[derive(Debug, Default, Serialize)]
+pub struct Section<'a> {
+    /// path relative to data folder like `post/2020`
+    pub path: String,
+    /// page of `_index.md` or `_index.json`
+    pub index: Option<&'a PageInfo>,
+    pub pages: Vec<&'a PageInfo>,
+    pub subsections: Vec<Section<'a>>,
+}
+
+impl<'a> Section<'a> {
+    fn entry(&mut self, path: &str) -> &mut Section<'a> {
+        let mut section = self;
+        for path in prefixes(path) {
+            let position = match section.subsections.iter().position(|s| s.path == path) {
+                Some(position) => position,
+                None => {
+                    section.subsections.push(Section {
+                        path: path.to_string(),
+                        ..Default::default()
+                    });
+                    section.subsections.len() - 1
+                }
+            };
+            section = &mut section.subsections[position];
+        }
+        section
+    }
+
+    fn sort(&mut self, sections: &Sections, outer: Option<SortBy>) {
+        let sort_by = self
+            .index
+            .and_then(|index| sections.defaults.get(Path::new(&index.file)))
+            .and_then(|defaults| defaults.as_ref().ok())
+            .and_then(|defaults| defaults.sort_by)
+            .or(outer);
+        self.pages
+            .sort_by(|one, other| sort_by.unwrap_or(SortBy::Datetime).compare(one, other));
+        self.subsections
+            .sort_by(|one, other| one.path.cmp(&other.path));
+        for subsection in &mut self.subsections {
+            subsection.sort(sections, sort_by);
+        }
+    }
+
+    /// section at path and index pages of sections containing it from the outermost
+    pub fn find(&self, path: &str) -> Option<(&Section<'a>, Vec<&'a PageInfo>)> {
+        let mut section = self;
+        let mut indexes: Vec<&PageInfo> = section.index.into_iter().collect();
+        for path in prefixes(path) {
+            section = section.subsections.iter().find(|s| s.path == path)?;
+            indexes.extend(section.index);
+        }
+        Some((section, indexes))
+    }
+}
+
+/// paths of sections from the outermost one to the one at path, like `post` and `post/2020`
+fn prefixes(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices('/')
+        .map(|(index, _)| index)
+        .chain(Some(path.len()))
+        .filter(move |_| !path.is_empty())
+        .map(move |index| &path[..index])
+}
+
+/// data of index page without rendering its content
+fn read_data(file: &Path) -> Result<HashMap<String, Value>, StapleError> {
+    match file.extension().and_then(|e| e.to_str()) {
+        Some("json") => {
+            let content = std::fs::read_to_string(file)?;
+            let value: Value = serde_json::from_str(&content)?;
+            Ok(match value.get("data") {
+                Some(Value::Object(data)) => data.clone().into_iter().collect(),
+                _ => HashMap::new(),
+            })
+        }
+        _ => markdown::read_data(&file.to_string_lossy()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        data::section::{SectionDefaults, Sections, SortBy},
+        test::setup,
+    };
+
+    #[test]
+    fn should_cascade_defaults_of_sections() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        std::fs::create_dir_all(dir.join("data/post/2020/bundle"))?;
+        std::fs::write(
+            dir.join("data/post/_index.md"),
+            " - title = Posts\n - url = post\n - datetime = 2020-01-01T00:00:00+08:00\n - template = section.html\n - page_template = post.html\n - url_prefix = post\n - sort_by = title\n",
+        )?;
+        std::fs::write(
+            dir.join("data/post/2020/_index.json"),
+            r#"{"title": "2020", "url": "2020", "template": "section.html", "datetime": "2020-01-01T00:00:00+08:00", "content": "", "data": {"page_draft": true}}"#,
+        )?;
+        std::fs::write(dir.join("data/post/2020/bundle/index.md"), "")?;
+        let sections = Sections::new(&dir);
+
+        let defaults = sections.defaults(dir.join("data/post/2020/a.md"))?;
+        assert_eq!(
+            SectionDefaults {
+                page_template: Some("post.html".to_string()),
+                url_prefix: Some("post".to_string()),
+                sort_by: Some(SortBy::Title),
+                page_draft: Some(true),
+            },
+            defaults
+        );
+        assert_eq!("/post/a", defaults.url("a".to_string()));
+        assert_eq!("/a", defaults.url("/a".to_string()));
+        assert_eq!(
+            None,
+            sections
+                .defaults(dir.join("data/post/_index.md"))?
+                .page_template
+        );
+        assert_eq!(
+            Some("post.html".to_string()),
+            sections
+                .defaults(dir.join("data/post/2020/_index.json"))?
+                .page_template
+        );
+
+        assert_eq!(
+            Some("".to_string()),
+            sections.path_of(dir.join("data/a.md"))
+        );
+        assert_eq!(
+            Some("post/2020".to_string()),
+            sections.path_of(dir.join("data/post/2020/_index.json"))
+        );
+        assert_eq!(
+            Some("post/2020".to_string()),
+            sections.path_of(dir.join("data/post/2020/bundle/index.md"))
+        );
+
+        std::fs::create_dir_all(dir.join("data/bad"))?;
+        std::fs::write(dir.join("data/bad/_index.md"), " - sort_by = size\n")?;
+        let sections = Sections::new(&dir);
+        for file in &["data/bad/a.md", "data/bad/_index.md"] {
+            let error = sections.defaults(dir.join(file)).unwrap_err().to_string();
+            assert!(error.contains("invalid section defaults"), "{}", error);
+        }
+        assert!(sections.defaults(dir.join("data/post/a.md")).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{
+    data::{
+        placeholder::{code_ranges, in_code, Placeholders},
+        types::markdown::toml_to_json,
+        MarkdownContent, MarkdownRenderer,
+    },
+    error::{display_render_error, StapleError},
+    util::hash::hash_bytes,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::{ops::Range, path::Path};
+use tera::{Context, Tera};
+use walkdir::WalkDir;
+
+pub const SHORTCODES_FOLDER: &str = "shortcodes";
+
+/// `{{ name(key="value") }}` or the opening tag `{% name(key="value") %}` of shortcode with body
+static SHORTCODE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?s)\{(?P<open>\{|%)\s*(?P<name>[A-Za-z_][\w-]*)\s*\((?P<args>.*?)\)\s*(?P<close>\}|%)\}",
+    )
+    .expect("invalid regex")
+});
+static BLOCK_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)\{%\s*(?:(?P<end>end)|[A-Za-z_][\w-]*\s*\(.*?\))\s*%\}")
+        .expect("invalid regex")
+});
+
+/// templates in `templates/<theme>/shortcodes`, shortcode `name` is rendered by `name.html`
+#[derive(Debug)]
+pub struct Shortcodes {
+    tera: Tera,
+    /// digest of all shortcode templates
+    pub digest: String,
+}
+
+impl Shortcodes {
+    pub fn load(folder: impl AsRef<Path>) -> Result<Option<Self>, StapleError> {
+        let folder = folder.as_ref();
+        if !folder.is_dir() {
+            return Ok(None);
+        }
+        let root = folder.to_str().expect("invalid file path");
+        let tera = Tera::new(&format!("{}/**/*", root))?;
+        let mut sources = vec![];
+        for entry in WalkDir::new(folder)
+            .sort_by(|one, other| one.file_name().cmp(other.file_name()))
+            .into_iter()
+            .flat_map(|e| e.ok())
+            .filter(|de| de.path().is_file())
+        {
+            sources.extend(entry.path().to_string_lossy().as_bytes());
+            sources.extend(std::fs::read(entry.path())?);
+        }
+        Ok(Some(Shortcodes {
+            tera,
+            digest: hash_bytes(sources),
+        }))
+    }
+
+    /// replace shortcodes outside of code with placeholders of their rendered html
+    pub fn expand(
+        &self,
+        raw: &str,
+        renderer: &MarkdownRenderer,
+        placeholders: &mut Placeholders,
+    ) -> Result<String, StapleError> {
+        let code = code_ranges(raw, renderer.config.options());
+        let mut markdown = String::new();
+        let mut position = 0;
+        let mut search = 0;
+        while let Some(captures) = SHORTCODE_REGEX.captures(&raw[search..]) {
+            let tag = captures.get(0).expect("invalid shortcode");
+            let (start, tag_end) = (search + tag.start(), search + tag.end());
+            let is_block = &captures["open"] == "%";
+            if is_block != (&captures["close"] == "%") || in_code(&code, start) {
+                search = start + 1;
+                continue;
+            }
+            let name = &captures["name"];
+            let (body, end) = if is_block {
+                let (body_end, end) = find_end(raw, tag_end, &code).ok_or_else(|| {
+                    shortcode_error(name, "missing closing `{% end %}`".to_string())
+                })?;
+                let body = MarkdownContent::render(raw[tag_end..body_end].to_string(), renderer)?;
+                (Some(body.html), end)
+            } else {
+                (None, tag_end)
+            };
+            let html = self.render(name, &captures["args"], body)?;
+            markdown.push_str(&raw[position..start]);
+            markdown.push_str(&placeholders.insert(html, String::new()));
+            position = end;
+            search = end;
+        }
+        markdown.push_str(&raw[position..]);
+        Ok(markdown)
+    }
+
+    fn render(&self, name: &str, args: &str, body: Option<String>) -> Result<String, StapleError> {
+        let template = format!("{}.html", name);
+        if self.tera.get_template(&template).is_err() {
+            return Err(shortcode_error(
+                name,
+                format!(
+                    "template `{}/{}` does not exist",
+                    SHORTCODES_FOLDER, template
+                ),
+            ));
+        }
+        let args = parse_args(args).map_err(|reason| shortcode_error(name, reason))?;
+        let mut context = Context::from_serialize(&args).expect("cannot serialize");
+        if let Some(body) = body {
+            context.insert("body", &body);
+        }
+        self.tera
+            .render(&template, &context)
+            .map_err(|e| shortcode_error(name, display_render_error(&e)))
+    }
+}
+
+fn shortcode_error(name: &str, reason: String) -> StapleError {
+    StapleError::ShortcodeError {
+        name: name.to_string(),
+        reason,
+    }
+}
+
+/// arguments are written like toml inline table, e.g. `id="abc", width=640, autoplay=true`
+fn parse_args(args: &str) -> Result<Map<String, Value>, String> {
+    let args = args.replace('\n', " ");
+    let table: toml::Value = toml::from_str(&format!("args = {{ {} }}", args.trim()))
+        .map_err(|e| format!("invalid arguments `{}`: {}", args.trim(), e))?;
+    match table.get("args").cloned().map(toml_to_json) {
+        Some(Value::Object(map)) => Ok(map),
+        _ => Ok(Map::new()),
+    }
+}
+
+/// end of body and end of the matching `{% end %}`, nested shortcodes with body are skipped
+fn find_end(raw: &str, from: usize, code: &[Range<usize>]) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    for tag in BLOCK_REGEX.captures_iter(&raw[from..]) {
+        let whole = tag.get(0).expect("invalid shortcode");
+        if in_code(code, from + whole.start()) {
+            continue;
+        }
+        match (tag.name("end"), depth) {
+            (Some(_), 0) => return Some((from + whole.start(), from + whole.end())),
+            (Some(_), _) => depth -= 1,
+            (None, _) => depth += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        data::{shortcode::Shortcodes, MarkdownContent, MarkdownRenderer},
+        test::setup,
+    };
+
+    fn renderer() -> MarkdownRenderer {
+        let dir = setup();
+        std::fs::create_dir_all(dir.join("shortcodes")).unwrap();
+        std::fs::write(
+            dir.join("shortcodes/video.html"),
+            r#"<video src="{{ src }}" width="{{ width }}"></video>"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("shortcodes/note.html"),
+            r#"<div class="note {{ kind }}">{{ body | safe }}</div>"#,
+        )
+        .unwrap();
+        MarkdownRenderer {
+            shortcodes: Shortcodes::load(dir.join("shortcodes")).unwrap(),
+            ..MarkdownRenderer::default()
+        }
+    }
+
+    #[test]
+    fn should_render_shortcodes() {
+        let renderer = renderer();
+        let content = MarkdownContent::render(
+            "see {{ video(src=\"a.mp4\", width=640) }} here\n\n{{ video(src='b.mp4', width=1) }}\n\n{% note(kind=\"warn\") %}\n**bold**\n\n{% note(kind=\"inner\") %}x{% end %}\n{% end %}\n".to_string(),
+            &renderer,
+        )
+        .unwrap();
+        assert_eq!(
+            "<p>see <video src=\"a.mp4\" width=\"640\"></video> here</p>\n<video src=\"b.mp4\" width=\"1\"></video><div class=\"note warn\"><p><strong>bold</strong></p>\n<div class=\"note inner\"><p>x</p>\n</div></div>",
+            content.html
+        );
+    }
+
+    #[test]
+    fn should_keep_shortcodes_in_code() {
+        let renderer = renderer();
+        let raw = "`{{ video(src=\"a\") }}`\n\n```\n{{ video(src=\"a\") }}\n```\n".to_string();
+        let content = MarkdownContent::render(raw.clone(), &renderer).unwrap();
+        assert_eq!(MarkdownContent::new(raw).html, content.html);
+    }
+
+    #[test]
+    fn should_report_shortcode_errors() {
+        let renderer = renderer();
+        for (raw, reason) in &[
+            (
+                "{{ missing() }}",
+                "template `shortcodes/missing.html` does not exist",
+            ),
+            ("{{ video(src=) }}", "invalid arguments `src=`"),
+            ("{{ video(src=\"a\") }}", "Variable `width` not found"),
+            ("{% note(kind=\"a\") %}body", "missing closing `{% end %}`"),
+        ] {
+            let error = MarkdownContent::render(raw.to_string(), &renderer)
+                .unwrap_err()
+                .to_string();
+            assert!(
+                error.contains(reason),
+                "{} should contain {}",
+                error,
+                reason
+            );
+        }
+    }
+}
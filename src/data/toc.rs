@@ -0,0 +1,169 @@
+use crate::{
+    config::HeadingAnchor,
+    data::{placeholder::Placeholders, MarkdownRenderer},
+    util::slug::slugify,
+};
+use pulldown_cmark::{Event, Tag};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// heading of markdown content, headings of deeper level are nested as children
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TocItem {
+    pub level: u32,
+    pub title: String,
+    pub id: String,
+    pub children: Vec<TocItem>,
+}
+
+/// give headings slug ids and anchor links, return events with table of contents.
+pub fn process_headings<'a>(
+    events: impl Iterator<Item = Event<'a>>,
+    renderer: &MarkdownRenderer,
+    placeholders: &Placeholders,
+) -> (Vec<Event<'a>>, Vec<TocItem>) {
+    let anchor = renderer.config.anchor;
+    let mut output = vec![];
+    let mut toc = vec![];
+    let mut ids = HashSet::new();
+    let mut heading: Option<Vec<Event<'a>>> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::Heading(_)) => heading = Some(vec![]),
+            Event::End(Tag::Heading(level)) => {
+                let inner = heading.take().unwrap_or_default();
+                let title: String = inner
+                    .iter()
+                    .filter_map(|event| match event {
+                        Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                        _ => None,
+                    })
+                    .collect();
+                let title = renderer.plain_text(&placeholders.plain_text(&title));
+                let title = title.trim().to_string();
+                let id = unique_id(&mut ids, slugify(&title));
+
+                output.push(Event::Html(format!(r#"<h{} id="{}">"#, level, id).into()));
+                if anchor == HeadingAnchor::Before {
+                    output.push(anchor_link(&id));
+                }
+                output.extend(inner);
+                if anchor == HeadingAnchor::After {
+                    output.push(anchor_link(&id));
+                }
+                output.push(Event::Html(format!("</h{}>\n", level).into()));
+                insert(
+                    &mut toc,
+                    TocItem {
+                        level,
+                        title,
+                        id,
+                        children: vec![],
+                    },
+                );
+            }
+            other => match heading.as_mut() {
+                Some(inner) => inner.push(other),
+                None => output.push(other),
+            },
+        }
+    }
+    (output, toc)
+}
+
+/// duplicated ids get suffix like `title-1`, `title-2`
+fn unique_id(ids: &mut HashSet<String>, slug: String) -> String {
+    let slug = if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    };
+    let mut id = slug.clone();
+    let mut count = 0;
+    while ids.contains(&id) {
+        count += 1;
+        id = format!("{}-{}", slug, count);
+    }
+    ids.insert(id.clone());
+    id
+}
+
+fn anchor_link<'a>(id: &str) -> Event<'a> {
+    Event::Html(
+        format!(
+            r##"<a class="anchor" href="#{}" aria-hidden="true">#</a>"##,
+            id
+        )
+        .into(),
+    )
+}
+
+fn insert(items: &mut Vec<TocItem>, item: TocItem) {
+    match items.last_mut() {
+        Some(last) if last.level < item.level => insert(&mut last.children, item),
+        _ => items.push(item),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::{HeadingAnchor, Markdown},
+        data::{MarkdownContent, MarkdownRenderer},
+    };
+
+    #[test]
+    fn should_build_nested_toc() {
+        let content = MarkdownContent::new(
+            "# One\n## Sub `code`\n### Deep\n## Sub code\n# Two\n### Skipped level\n# One\n"
+                .to_string(),
+        );
+        let toc: Vec<(&str, &str, usize)> = content
+            .toc
+            .iter()
+            .map(|item| (item.title.as_str(), item.id.as_str(), item.children.len()))
+            .collect();
+        assert_eq!(
+            vec![("One", "one", 2), ("Two", "two", 1), ("One", "one-1", 0)],
+            toc
+        );
+        let subs: Vec<&str> = content.toc[0]
+            .children
+            .iter()
+            .map(|item| item.id.as_str())
+            .collect();
+        assert_eq!(vec!["sub-code", "sub-code-1"], subs);
+        assert_eq!("deep", content.toc[0].children[0].children[0].id);
+        assert!(content
+            .html
+            .contains(r#"<h2 id="sub-code">Sub <code>code</code></h2>"#));
+    }
+
+    #[test]
+    fn should_slugify_cjk_and_ruby_heading() {
+        let content = MarkdownContent::new("## 这是一个{RUBY带中文}(中文下标)标签".to_string());
+        assert_eq!("这是一个RUBY带中文标签", content.toc[0].title);
+        assert_eq!("这是一个ruby带中文标签", content.toc[0].id);
+        assert_eq!(
+            "<h2 id=\"这是一个ruby带中文标签\">这是一个<ruby>RUBY带中文<rp>(</rp><rt>中文下标</rt><rp>)</rp></ruby>标签</h2>\n",
+            content.html
+        );
+    }
+
+    #[test]
+    fn should_render_anchor_link() {
+        let config = Markdown {
+            anchor: HeadingAnchor::After,
+            ..Default::default()
+        };
+        let content = MarkdownContent::render(
+            "# Title".to_string(),
+            &MarkdownRenderer::new(config).expect("invalid config"),
+        )
+        .expect("cannot render markdown");
+        assert_eq!(
+            "<h1 id=\"title\">Title<a class=\"anchor\" href=\"#title\" aria-hidden=\"true\">#</a></h1>\n",
+            content.html
+        );
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
     constants::DESCRIPTION_SEPARATOR,
     data::{
         types::{CreationOptions, FileType},
-        MarkdownContent, PageInfo,
+        MarkdownContent, MarkdownRenderer, PageInfo,
     },
     error::StapleError,
 };
@@ -31,9 +31,11 @@ pub struct JsonFileData {
 struct InnerData {
     pub title: String,
     pub url: String,
-    pub template: String,
-    #[serde(default)]
-    pub draw: bool,
+    /// default template of section is used if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub draw: Option<bool>,
     pub datetime: DateTime<FixedOffset>,
     pub data: HashMap<String, Value>,
     pub content: String,
@@ -42,27 +44,44 @@ struct InnerData {
 impl FileType for JsonFileData {
     type Output = JsonFileData;
 
-    fn load(file: impl AsRef<Path>) -> Result<Self::Output, StapleError> {
+    fn load(
+        file: impl AsRef<Path>,
+        renderer: &MarkdownRenderer,
+    ) -> Result<Self::Output, StapleError> {
         let file = file.as_ref();
         let data_file_content = std::fs::read_to_string(file)?;
 
         let data = serde_json::from_str::<InnerData>(&data_file_content)?;
+        let defaults = renderer.section_defaults(file)?;
+        let url = defaults.url(data.url);
+        let template =
+            data.template
+                .or(defaults.page_template)
+                .ok_or(StapleError::ArticleError {
+                    filename: file.to_string_lossy().to_string(),
+                    reason: "template does not exist in article's data".to_string(),
+                })?;
+        let bundle = renderer.bundle(file, &url);
         let description = if data.content.contains(DESCRIPTION_SEPARATOR) {
             let content_split: Vec<&str> = data.content.splitn(2, DESCRIPTION_SEPARATOR).collect();
-            Some(MarkdownContent::new(content_split[0].to_string()))
+            Some(MarkdownContent::render_page(
+                content_split[0].to_string(),
+                renderer,
+                bundle.as_ref(),
+            )?)
         } else {
             None
         };
         Ok(Self {
             path: file.to_str().unwrap().to_string(),
-            url: data.url,
+            url,
             title: data.title,
-            template: data.template,
-            draw: data.draw,
+            template,
+            draw: data.draw.or(defaults.page_draft).unwrap_or(false),
             datetime: data.datetime,
             data: data.data,
             description,
-            content: MarkdownContent::new(data.content),
+            content: MarkdownContent::render_page(data.content, renderer, bundle.as_ref())?,
         })
     }
 
@@ -71,8 +90,8 @@ impl FileType for JsonFileData {
         let data = InnerData {
             title: options.title.clone(),
             url: options.url.clone(),
-            template: options.template.clone(),
-            draw: options.draw,
+            template: Some(options.template.clone()),
+            draw: Some(options.draw),
             datetime: Utc::now().with_timezone(&offset),
             data: HashMap::new(),
             content: "".to_string(),
@@ -1,14 +1,16 @@
 use std::collections::HashMap;
 
 use chrono::{DateTime, FixedOffset, Utc};
-use pest::Parser;
+use pest::{error::LineColLocation, Parser};
 use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::{
+    config::FrontMatterStyle,
     constants::{DESCRIPTION_SEPARATOR, LINE_ENDING},
     data::{
         types::{CreationOptions, FileType},
-        MarkdownContent, PageInfo,
+        MarkdownContent, MarkdownRenderer, PageInfo,
     },
     error::StapleError,
 };
@@ -28,6 +30,16 @@ pub struct ArticleMeta {
     pub description: Option<MarkdownContent>,
 }
 
+/// metadata written into yaml or toml front matter
+#[derive(Serialize)]
+struct FrontMatter<'a> {
+    title: &'a str,
+    url: &'a str,
+    datetime: &'a str,
+    template: &'a str,
+    draw: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct MarkdownFileData {
     pub path: String,
@@ -37,7 +49,7 @@ pub struct MarkdownFileData {
     pub datetime: DateTime<FixedOffset>,
     #[serde(default)]
     pub draw: bool,
-    pub data: HashMap<String, serde_json::Value>,
+    pub data: HashMap<String, Value>,
     pub content: MarkdownContent,
     pub description: Option<MarkdownContent>,
 }
@@ -45,64 +57,50 @@ pub struct MarkdownFileData {
 impl FileType for MarkdownFileData {
     type Output = MarkdownFileData;
 
-    fn load(file: impl AsRef<Path>) -> Result<Self::Output, StapleError> {
+    fn load(
+        file: impl AsRef<Path>,
+        renderer: &MarkdownRenderer,
+    ) -> Result<Self::Output, StapleError> {
         let file = file.as_ref().to_str().unwrap();
         debug!("load article {}", &file);
-        let string = std::fs::read_to_string(file)?;
-        let mut metas: HashMap<String, String> = HashMap::new();
-        let mut content = String::new();
-
-        let parse1 = ArticleParser::parse(Rule::article, &string);
-        let x = parse1
-            .expect("unknown error on parsing markdown")
-            .next()
-            .expect("unknown error on parsing markdown");
-        for pair in x.into_inner() {
-            match pair.as_rule() {
-                Rule::meta => {
-                    for meta in pair.into_inner() {
-                        let mut x1 = meta.into_inner();
-                        let key: String = x1
-                            .next()
-                            .expect("unknown error on parsing markdown")
-                            .as_str()
-                            .to_string();
-                        let value: String = x1
-                            .next()
-                            .expect("unknown error on parsing markdown")
-                            .as_str()
-                            .to_string();
-                        metas.insert(key.to_lowercase(), value);
-                    }
-                }
-                Rule::content => {
-                    content.push_str(pair.as_str());
-                }
-                Rule::EOI => (),
-                _ => unreachable!(),
-            }
-        }
+        let (mut metas, is_inline, content) = parse(file)?;
+        let defaults = renderer.section_defaults(file)?;
+
+        let url = metas
+            .remove("url")
+            .map(value_to_string)
+            .map(|url| defaults.url(url))
+            .ok_or(StapleError::ArticleError {
+                filename: file.to_string(),
+                reason: "url does not exist in article's metadata".to_string(),
+            })?;
+        let title =
+            metas
+                .remove("title")
+                .map(value_to_string)
+                .ok_or(StapleError::ArticleError {
+                    filename: file.to_string(),
+                    reason: "title does not exist in article's metadata".to_string(),
+                })?;
+        let template = metas
+            .remove("template")
+            .map(value_to_string)
+            .or(defaults.page_template)
+            .ok_or(StapleError::ArticleError {
+                filename: file.to_string(),
+                reason: "template does not exist in article's metadata".to_string(),
+            })?;
 
-        let url = metas.remove("url").ok_or(StapleError::ArticleError {
-            filename: file.to_string(),
-            reason: "url does not exist in article's metadata".to_string(),
-        })?;
-        let title = metas.remove("title").ok_or(StapleError::ArticleError {
-            filename: file.to_string(),
-            reason: "title does not exist in article's metadata".to_string(),
-        })?;
-        let template = metas.remove("template").ok_or(StapleError::ArticleError {
-            filename: file.to_string(),
-            reason: "template does not exist in article's metadata".to_string(),
-        })?;
-
-        let draw = metas
-            .remove("draw")
-            .map(|value| value.to_lowercase().eq("true"))
-            .unwrap_or(false);
+        let draw = match metas.remove("draw").or_else(|| metas.remove("draft")) {
+            Some(Value::Bool(draw)) => draw,
+            Some(value) => value_to_string(value).to_lowercase().eq("true"),
+            None => defaults.page_draft.unwrap_or(false),
+        };
 
         let option_date = metas
             .remove("datetime")
+            .or_else(|| metas.remove("date"))
+            .map(value_to_string)
             .ok_or(StapleError::ArticleError {
                 filename: file.to_string(),
                 reason: "datetime does not exist in article's metadata".to_string(),
@@ -113,22 +111,17 @@ impl FileType for MarkdownFileData {
                 reason: format!("parse date error {}", e),
             })?;
 
+        let bundle = renderer.bundle(file, &url);
         let description = if content.contains(DESCRIPTION_SEPARATOR) {
             let content_split: Vec<&str> = content.splitn(2, DESCRIPTION_SEPARATOR).collect();
-            Some(MarkdownContent::new(content_split[0].to_string()))
+            Some(MarkdownContent::render_page(
+                content_split[0].to_string(),
+                renderer,
+                bundle.as_ref(),
+            )?)
         } else {
             None
         };
-        let extra_json_data = metas
-            .into_iter()
-            .map(|(key, value)| {
-                let json_value = match serde_json::from_str::<serde_json::Value>(&value) {
-                    Ok(val) => val,
-                    Err(_) => serde_json::Value::String(value),
-                };
-                (key, json_value)
-            })
-            .collect();
 
         Ok(MarkdownFileData {
             path: file.to_owned(),
@@ -137,8 +130,8 @@ impl FileType for MarkdownFileData {
             template,
             datetime: option_date,
             description,
-            content: MarkdownContent::new(content),
-            data: extra_json_data,
+            content: MarkdownContent::render_page(content, renderer, bundle.as_ref())?,
+            data: into_data(metas, is_inline),
             draw,
         })
     }
@@ -147,16 +140,42 @@ impl FileType for MarkdownFileData {
         let offset = FixedOffset::east(60 * 60 * 8);
         let datetime = Utc::now().with_timezone(&offset).to_rfc3339();
 
-        let mut content = String::new();
-
-        content.push_str(&format!(" - title = {}{}", &options.title, LINE_ENDING));
-        content.push_str(&format!(" - url = {}{}", &options.url, LINE_ENDING));
-        content.push_str(&format!(" - datetime = {}{}", datetime, LINE_ENDING));
-        content.push_str(&format!(
-            " - template = {}{}",
-            options.template, LINE_ENDING
-        ));
-        content.push_str(&format!(" - draw = {}{}", options.draw, LINE_ENDING));
+        let mut content = match options.front_matter {
+            FrontMatterStyle::Inline => {
+                let mut content = String::new();
+                content.push_str(&format!(" - title = {}{}", &options.title, LINE_ENDING));
+                content.push_str(&format!(" - url = {}{}", &options.url, LINE_ENDING));
+                content.push_str(&format!(" - datetime = {}{}", datetime, LINE_ENDING));
+                content.push_str(&format!(
+                    " - template = {}{}",
+                    options.template, LINE_ENDING
+                ));
+                content.push_str(&format!(" - draw = {}{}", options.draw, LINE_ENDING));
+                content
+            }
+            FrontMatterStyle::Yaml | FrontMatterStyle::Toml => {
+                let front_matter = FrontMatter {
+                    title: &options.title,
+                    url: &options.url,
+                    datetime: &datetime,
+                    template: &options.template,
+                    draw: options.draw,
+                };
+                let (separator, raw) = if options.front_matter == FrontMatterStyle::Yaml {
+                    let raw = serde_yaml::to_string(&front_matter)
+                        .map_err(|e| StapleError::FrontMatterError(e.to_string()))?;
+                    ("---", raw.trim_start_matches("---").trim().to_string())
+                } else {
+                    let raw = toml::to_string(&front_matter)
+                        .map_err(|e| StapleError::FrontMatterError(e.to_string()))?;
+                    ("+++", raw.trim().to_string())
+                };
+                [separator, &raw, separator]
+                    .iter()
+                    .map(|line| format!("{}{}", line, LINE_ENDING))
+                    .collect()
+            }
+        };
         content.push_str(LINE_ENDING);
 
         let file_name = options.title.trim().replace(" ", "-").replace("_", "-");
@@ -181,3 +200,253 @@ impl FileType for MarkdownFileData {
         }
     }
 }
+
+/// metadata of article without rendering its content, values of inline meta are treated as json
+pub(crate) fn read_data(file: &str) -> Result<HashMap<String, Value>, StapleError> {
+    let (metas, is_inline, _) = parse(file)?;
+    Ok(into_data(metas, is_inline))
+}
+
+/// metadata, whether it is written in inline style, and markdown content of article
+fn parse(file: &str) -> Result<(HashMap<String, Value>, bool, String), StapleError> {
+    let string = std::fs::read_to_string(file)?;
+    let mut metas: HashMap<String, Value> = HashMap::new();
+    let mut is_inline = true;
+    let mut content = String::new();
+
+    let x = ArticleParser::parse(Rule::article, &string)
+        .map_err(|e| syntax_error(file, e))?
+        .next()
+        .expect("unknown error on parsing markdown");
+    for pair in x.into_inner() {
+        match pair.as_rule() {
+            Rule::meta => {
+                for meta in pair.into_inner() {
+                    let mut x1 = meta.into_inner();
+                    let key: String = x1
+                        .next()
+                        .expect("unknown error on parsing markdown")
+                        .as_str()
+                        .to_string();
+                    let value: String = x1
+                        .next()
+                        .expect("unknown error on parsing markdown")
+                        .as_str()
+                        .to_string();
+                    metas.insert(key.to_lowercase(), Value::String(value));
+                }
+            }
+            Rule::yamlFrontMatter | Rule::tomlFrontMatter => {
+                is_inline = false;
+                let is_yaml = pair.as_rule() == Rule::yamlFrontMatter;
+                let raw = pair
+                    .into_inner()
+                    .next()
+                    .expect("unknown error on parsing markdown")
+                    .as_str();
+                metas = parse_front_matter(raw, is_yaml).map_err(|reason| {
+                    StapleError::ArticleError {
+                        filename: file.to_string(),
+                        reason,
+                    }
+                })?;
+            }
+            Rule::content => {
+                content.push_str(pair.as_str());
+            }
+            Rule::EOI => (),
+            _ => unreachable!(),
+        }
+    }
+    Ok((metas, is_inline, content))
+}
+
+/// values of inline meta are raw text, try to treat them as json
+fn into_data(metas: HashMap<String, Value>, is_inline: bool) -> HashMap<String, Value> {
+    metas
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(raw) if is_inline => {
+                let json_value = match serde_json::from_str::<Value>(&raw) {
+                    Ok(val) => val,
+                    Err(_) => Value::String(raw),
+                };
+                (key, json_value)
+            }
+            value => (key, value),
+        })
+        .collect()
+}
+
+/// turn pest error into `ArticleSyntaxError`, rules are renamed to be readable in snippet
+fn syntax_error(file: &str, error: pest::error::Error<Rule>) -> StapleError {
+    let (line, column) = match error.line_col {
+        LineColLocation::Pos(pos) => pos,
+        LineColLocation::Span(start, _) => start,
+    };
+    let error = error.renamed_rules(|rule| {
+        match rule {
+            Rule::meta | Rule::inlineMeta => "meta line like ` - key = value`",
+            Rule::inlineKey => "meta key",
+            Rule::inlineValue => "meta value",
+            Rule::yamlFrontMatter | Rule::yamlContent => "yaml front matter wrapped by `---`",
+            Rule::tomlFrontMatter | Rule::tomlContent => "toml front matter wrapped by `+++`",
+            Rule::article | Rule::content | Rule::EOI => "content",
+        }
+        .to_string()
+    });
+    StapleError::ArticleSyntaxError {
+        filename: file.to_string(),
+        line,
+        column,
+        snippet: error.to_string(),
+    }
+}
+
+fn parse_front_matter(raw: &str, is_yaml: bool) -> Result<HashMap<String, Value>, String> {
+    let value = if is_yaml {
+        if raw.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+        serde_yaml::from_str::<Value>(raw)
+            .map_err(|e| format!("invalid yaml front matter: {}", e))?
+    } else {
+        toml_to_json(toml::from_str(raw).map_err(|e| format!("invalid toml front matter: {}", e))?)
+    };
+    match value {
+        Value::Object(map) => Ok(map.into_iter().collect()),
+        Value::Null => Ok(HashMap::new()),
+        _ => Err("front matter should be a key-value mapping".to_string()),
+    }
+}
+
+/// toml datetime is kept as its rfc3339 string
+pub(crate) fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(value) => Value::String(value),
+        toml::Value::Integer(value) => Value::from(value),
+        toml::Value::Float(value) => Value::from(value),
+        toml::Value::Boolean(value) => Value::Bool(value),
+        toml::Value::Datetime(value) => Value::String(value.to_string()),
+        toml::Value::Array(values) => Value::Array(values.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn value_to_string(value: Value) -> String {
+    match value {
+        Value::String(value) => value,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::FrontMatterStyle,
+        data::types::{markdown::MarkdownFileData, CreationOptions, FileType},
+        error::StapleError,
+        test::setup,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn should_load_yaml_front_matter() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        let file = dir.join("yaml.md");
+        std::fs::write(
+            &file,
+            "---\ntitle: \"Hello: World\"\nurl: /hello\ntemplate: article.html\ndate: 2020-01-01T00:00:00+08:00\ndraft: true\ntags: [rust, web]\nextra:\n  cover: a.png\n---\n# content\n",
+        )?;
+        let data = MarkdownFileData::load(&file, &Default::default())?;
+        assert_eq!("Hello: World", data.title);
+        assert_eq!("/hello", data.url);
+        assert!(data.draw);
+        assert_eq!("2020-01-01T00:00:00+08:00", data.datetime.to_rfc3339());
+        assert_eq!(json!(["rust", "web"]), data.data["tags"]);
+        assert_eq!(json!({"cover": "a.png"}), data.data["extra"]);
+        assert_eq!("# content\n", data.content.markdown);
+        Ok(())
+    }
+
+    #[test]
+    fn should_load_toml_front_matter() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        let file = dir.join("toml.md");
+        std::fs::write(
+            &file,
+            "+++\ntitle = \"Hello\"\nurl = \"/hello\"\ntemplate = \"article.html\"\ndatetime = 2020-01-01T00:00:00+08:00\n\n[extra]\norder = 1\n+++\ncontent",
+        )?;
+        let data = MarkdownFileData::load(&file, &Default::default())?;
+        assert_eq!("Hello", data.title);
+        assert!(!data.draw);
+        assert_eq!("2020-01-01T00:00:00+08:00", data.datetime.to_rfc3339());
+        assert_eq!(json!({"order": 1}), data.data["extra"]);
+        assert_eq!("content", data.content.markdown);
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_invalid_front_matter() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        let file = dir.join("invalid.md");
+        std::fs::write(&file, "---\ntitle: [\n---\ncontent")?;
+        let error = MarkdownFileData::load(&file, &Default::default()).unwrap_err();
+        assert!(error.to_string().contains("invalid yaml front matter"));
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_position_of_syntax_error() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        let file = dir.join("broken.md");
+        std::fs::write(&file, "\n - = value\n")?;
+        match MarkdownFileData::load(&file, &Default::default()) {
+            Err(StapleError::ArticleSyntaxError {
+                filename,
+                line,
+                column,
+                snippet,
+            }) => {
+                assert!(filename.ends_with("broken.md"));
+                assert_eq!((2, 4), (line, column));
+                assert!(snippet.contains("2 |  - = value"));
+                assert!(snippet.contains("expected meta key"));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_create_with_configured_front_matter() -> Result<(), Box<dyn std::error::Error>> {
+        for style in &[
+            FrontMatterStyle::Inline,
+            FrontMatterStyle::Yaml,
+            FrontMatterStyle::Toml,
+        ] {
+            let dir = setup();
+            std::fs::create_dir(dir.join("data"))?;
+            let options = CreationOptions {
+                title: "a: b".to_string(),
+                url: "/a".to_string(),
+                template: "article.html".to_string(),
+                draw: true,
+                front_matter: *style,
+            };
+            MarkdownFileData::create(&dir, &options)?;
+            let file = dir.join("data/a:-b.md");
+            let data = MarkdownFileData::load(&file, &Default::default())?;
+            assert_eq!("a: b", data.title);
+            assert_eq!("/a", data.url);
+            assert!(data.draw);
+            assert!(data.data.is_empty());
+        }
+        Ok(())
+    }
+}
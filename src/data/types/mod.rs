@@ -1,4 +1,8 @@
-use crate::{data::PageInfo, error::StapleError};
+use crate::{
+    config::FrontMatterStyle,
+    data::{MarkdownRenderer, PageInfo},
+    error::StapleError,
+};
 use std::path::Path;
 
 pub(crate) mod json;
@@ -9,11 +13,15 @@ pub struct CreationOptions {
     pub url: String,
     pub template: String,
     pub draw: bool,
+    pub front_matter: FrontMatterStyle,
 }
 
 pub trait FileType {
     type Output;
-    fn load(file: impl AsRef<Path>) -> Result<Self::Output, StapleError>;
+    fn load(
+        file: impl AsRef<Path>,
+        renderer: &MarkdownRenderer,
+    ) -> Result<Self::Output, StapleError>;
     fn create(file: impl AsRef<Path>, options: &CreationOptions) -> Result<(), StapleError>;
     fn into_page_info(self) -> PageInfo;
 }
@@ -1,3 +1,4 @@
+use itertools::Itertools;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,18 +12,95 @@ pub enum StapleError {
     #[error("config error {}", .0.to_string())]
     ConfigError(#[from] toml::de::Error),
 
-    #[error("render error {}", .0.to_string())]
+    #[error("render error {}", display_render_error(.0))]
     RenderError(#[from] tera::Error),
 
+    #[error("error on rendering shortcode `{name}`: {reason}")]
+    ShortcodeError { name: String, reason: String },
+
+    #[error("template `{0}` does not exist")]
+    TemplateNotFound(String),
+
+    #[error("output format `{0}` does not exist")]
+    OutputFormatNotFound(String),
+
+    #[error("statics file `{0}` does not exist")]
+    StaticsNotFound(String),
+
+    #[error("invalid statics `{from}`: {reason}")]
+    StaticsError { from: String, reason: String },
+
+    #[error("broken link `{link}`: {reason}")]
+    BrokenLink { link: String, reason: String },
+
+    #[error("`{output}` is produced by multiple files: {}", .files.join(", "))]
+    DuplicateUrl { output: String, files: Vec<String> },
+
+    #[error("terms {} share the same url `{url}`", .names.join(", "))]
+    TermCollision { url: String, names: Vec<String> },
+
     #[error("error on loading article {filename} : {reason}")]
     ArticleError { filename: String, reason: String },
 
+    #[error("error on parsing article {filename} at line {line}, column {column}:\n{snippet}")]
+    ArticleSyntaxError {
+        filename: String,
+        line: usize,
+        column: usize,
+        snippet: String,
+    },
+
     #[error("error on parse url: {}", .0.to_string())]
     UrlParseError(#[from] url::ParseError),
 
     #[error("cannot serde json file: {0}")]
     JsonFileParseError(#[from] serde_json::Error),
 
+    #[error("cannot serialize front matter: {0}")]
+    FrontMatterError(String),
+
     #[error("execute hook `{}` get non-zero exit code: {}", .0, .1.unwrap_or(-1))]
     HookError(String, Option<i32>),
+
+    #[error("markdown extension `{0}` does not exist")]
+    ExtensionNotFound(String),
+
+    #[error("error on rendering {language} diagram: {reason}")]
+    DiagramError { language: String, reason: String },
+
+    #[error("error on processing image {path}: {reason}")]
+    ImageError { path: String, reason: String },
+
+    #[error("error on compiling stylesheet {file}: {reason}")]
+    SassError { file: String, reason: String },
+
+    #[error("highlight theme `{0}` does not exist")]
+    HighlightThemeNotFound(String),
+
+    #[error("cannot build worker pool: {0}")]
+    ThreadPoolError(#[from] rayon::ThreadPoolBuildError),
+
+    #[error("fail to process {} page(s):\n{}", .0.len(), display_page_errors(.0))]
+    PagesError(Vec<(String, StapleError)>),
+
+    #[error("found {} problem(s):\n{}", .0.len(), display_page_errors(.0))]
+    CheckError(Vec<(String, StapleError)>),
+}
+
+/// tera puts the actual reason like missing variable or function error into source
+pub(crate) fn display_render_error(error: &tera::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(e) = source {
+        message.push_str(&format!(": {}", e));
+        source = e.source();
+    }
+    message
+}
+
+fn display_page_errors(errors: &[(String, StapleError)]) -> String {
+    errors
+        .iter()
+        .map(|(file, e)| format!("  - {}: {}", file, e))
+        .join("\n")
 }
@@ -0,0 +1,263 @@
+use crate::{
+    cache::BuildCache,
+    config::{Config, Feed, FeedFormat},
+    constants::PUBLIC_FOLDER,
+    data::{section::is_section_index, MarkdownRenderer, PageInfo},
+    error::StapleError,
+    util::{filter::is_field_false, xml::escape},
+};
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use std::path::Path;
+
+#[derive(Debug)]
+pub struct FeedItem {
+    pub title: String,
+    pub link: String,
+    pub datetime: DateTime<FixedOffset>,
+    pub content: String,
+}
+
+/// generate feed files configured in `[feed]` section into output folder.
+pub fn render(
+    articles: &[PageInfo],
+    config: &Config,
+    renderer: &MarkdownRenderer,
+    path: impl AsRef<Path>,
+    cache: &BuildCache,
+    current: &mut BuildCache,
+) -> Result<(), StapleError> {
+    let feed = match &config.feed {
+        Some(feed) => feed,
+        None => return Ok(()),
+    };
+    let items = collect_items(articles, config, renderer, feed)?;
+    let updated = items
+        .first()
+        .map(|item| item.datetime)
+        .unwrap_or_else(|| newest(articles));
+    let output_folder = path.as_ref().join(PUBLIC_FOLDER);
+    for format in &feed.formats {
+        info!("generating feed {}", format.output_file());
+        let content = match format {
+            FeedFormat::Rss => render_rss(config, &items),
+            FeedFormat::Atom => render_atom(config, &items, updated),
+        };
+        current.write(cache, &output_folder, format.output_file(), content)?;
+    }
+    Ok(())
+}
+
+/// datetime of the newest page, or unix epoch without any, so that feed stays the same between
+/// builds
+fn newest(articles: &[PageInfo]) -> DateTime<FixedOffset> {
+    articles
+        .iter()
+        .filter(|article| !is_section_index(&article.file))
+        .map(|article| article.datetime)
+        .max()
+        .unwrap_or_else(|| Utc.timestamp(0, 0).into())
+}
+
+fn collect_items(
+    articles: &[PageInfo],
+    config: &Config,
+    renderer: &MarkdownRenderer,
+    feed: &Feed,
+) -> Result<Vec<FeedItem>, StapleError> {
+    let mut items = vec![];
+    for article in articles {
+        if items.len() >= feed.limit {
+            break;
+        }
+        if is_section_index(&article.file) {
+            continue;
+        }
+        if let Some(key) = &feed.exclude {
+            if !is_field_false(&serde_json::to_value(article)?, key) {
+                continue;
+            }
+        }
+        let content = match (&article.description, feed.full_content) {
+            (Some(description), false) => description.html.clone(),
+            _ => article.to_full_article(renderer)?.content().html.clone(),
+        };
+        items.push(FeedItem {
+            title: article.title.clone(),
+            link: config.site.absolute_url(&article.url),
+            datetime: article.datetime,
+            content,
+        });
+    }
+    Ok(items)
+}
+
+pub fn render_rss(config: &Config, items: &[FeedItem]) -> String {
+    let site = &config.site;
+    let mut output = String::new();
+    output.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    output.push_str(r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>"#);
+    output.push_str(&format!("<title>{}</title>", escape(&site.title)));
+    output.push_str(&format!("<link>{}</link>", escape(&site.absolute_url("/"))));
+    output.push_str(&format!(
+        r#"<atom:link href="{}" rel="self" type="application/rss+xml"/>"#,
+        escape(&site.absolute_url(FeedFormat::Rss.output_file()))
+    ));
+    output.push_str(&format!(
+        "<description>{}</description>",
+        escape(&site.description)
+    ));
+    if let Some(item) = items.first() {
+        output.push_str(&format!(
+            "<lastBuildDate>{}</lastBuildDate>",
+            item.datetime.to_rfc2822()
+        ));
+    }
+    for item in items {
+        output.push_str("<item>");
+        output.push_str(&format!("<title>{}</title>", escape(&item.title)));
+        output.push_str(&format!("<link>{}</link>", escape(&item.link)));
+        output.push_str(&format!("<guid>{}</guid>", escape(&item.link)));
+        output.push_str(&format!(
+            "<pubDate>{}</pubDate>",
+            item.datetime.to_rfc2822()
+        ));
+        output.push_str(&format!(
+            "<description>{}</description>",
+            escape(&item.content)
+        ));
+        output.push_str("</item>");
+    }
+    output.push_str("</channel></rss>");
+    output
+}
+
+/// atom feed `updated` at the given datetime, which is that of the newest item if any
+pub fn render_atom(config: &Config, items: &[FeedItem], updated: DateTime<FixedOffset>) -> String {
+    let site = &config.site;
+    let mut output = String::new();
+    output.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    output.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    output.push_str(&format!("<title>{}</title>", escape(&site.title)));
+    if !site.subtitle.is_empty() {
+        output.push_str(&format!("<subtitle>{}</subtitle>", escape(&site.subtitle)));
+    }
+    output.push_str(&format!(
+        r#"<link href="{}"/>"#,
+        escape(&site.absolute_url("/"))
+    ));
+    output.push_str(&format!(
+        r#"<link href="{}" rel="self"/>"#,
+        escape(&site.absolute_url(FeedFormat::Atom.output_file()))
+    ));
+    output.push_str(&format!("<id>{}</id>", escape(&site.absolute_url("/"))));
+    output.push_str(&format!("<updated>{}</updated>", updated.to_rfc3339()));
+    output.push_str(&format!(
+        "<author><name>{}</name></author>",
+        escape(&site.author)
+    ));
+    for item in items {
+        output.push_str("<entry>");
+        output.push_str(&format!("<title>{}</title>", escape(&item.title)));
+        output.push_str(&format!(r#"<link href="{}"/>"#, escape(&item.link)));
+        output.push_str(&format!("<id>{}</id>", escape(&item.link)));
+        output.push_str(&format!(
+            "<updated>{}</updated>",
+            item.datetime.to_rfc3339()
+        ));
+        output.push_str(&format!(
+            r#"<content type="html">{}</content>"#,
+            escape(&item.content)
+        ));
+        output.push_str("</entry>");
+    }
+    output.push_str("</feed>");
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        app::App,
+        command::{
+            add::{add, AddOptions},
+            build::build,
+        },
+        config::Config,
+        feed::{render_atom, render_rss, FeedItem},
+        test::setup,
+    };
+    use chrono::DateTime;
+
+    fn item() -> FeedItem {
+        FeedItem {
+            title: "Tom & Jerry".to_string(),
+            link: "https://example.com/tom".to_string(),
+            datetime: DateTime::parse_from_rfc3339("2020-01-02T03:04:05+08:00").unwrap(),
+            content: "<p>hello</p>".to_string(),
+        }
+    }
+
+    #[test]
+    fn should_render_rss_item() {
+        let mut config = Config::default();
+        config.site.domain = "https://example.com".to_string();
+        let rss = render_rss(&config, &[item()]);
+        assert!(rss.contains("<title>Tom &amp; Jerry</title>"));
+        assert!(rss.contains("<link>https://example.com/tom</link>"));
+        assert!(rss.contains("<pubDate>Thu, 02 Jan 2020 03:04:05 +0800</pubDate>"));
+        assert!(rss.contains("<description>&lt;p&gt;hello&lt;/p&gt;</description>"));
+        assert!(rss.contains(r#"<atom:link href="https://example.com/rss.xml""#));
+    }
+
+    #[test]
+    fn should_render_atom_entry() {
+        let config = Config::default();
+        let atom = render_atom(&config, &[item()], item().datetime);
+        assert!(atom.contains("<updated>2020-01-02T03:04:05+08:00</updated>"));
+        assert!(atom.contains(r#"<link href="https://example.com/tom"/>"#));
+        assert!(atom.contains(r#"<content type="html">&lt;p&gt;hello&lt;/p&gt;</content>"#));
+    }
+
+    #[test]
+    fn should_generate_feed_files() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let config_file = dir.join("Staple.toml");
+        let config = std::fs::read_to_string(&config_file)?;
+        std::fs::write(
+            &config_file,
+            config.replace("[extra]", "[feed]\nlimit = 1\n\n[extra]"),
+        )?;
+        for title in &["one", "two"] {
+            let options = AddOptions {
+                title: title.to_string(),
+                url: None,
+                template: None,
+                draw: false,
+                data: false,
+            };
+            add(&dir, options)?;
+        }
+        build(&dir, false, None)?;
+
+        let rss = std::fs::read_to_string(dir.join("public/rss.xml"))?;
+        assert_eq!(1, rss.matches("<item>").count());
+        assert!(dir.join("public/atom.xml").exists());
+
+        std::fs::write(
+            &config_file,
+            config.replace("[extra]", "[feed]\nexclude = \"title\"\n\n[extra]"),
+        )?;
+        build(&dir, false, None)?;
+        let atom = std::fs::read_to_string(dir.join("public/atom.xml"))?;
+        assert_eq!(0, atom.matches("<entry>").count());
+        let newest = super::newest(&App::load(&dir, false)?.load_all_data()?);
+        assert!(atom.contains(&format!("<updated>{}</updated>", newest.to_rfc3339())));
+        assert_eq!("1970-01-01T00:00:00+00:00", super::newest(&[]).to_rfc3339());
+
+        std::fs::write(&config_file, config)?;
+        build(&dir, false, None)?;
+        assert!(!dir.join("public/rss.xml").exists());
+        Ok(())
+    }
+}
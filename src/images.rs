@@ -0,0 +1,514 @@
+use crate::{
+    cache::BuildCache,
+    config::{Config, ResponsiveImages, Site},
+    data::bundle::Bundle,
+    error::StapleError,
+    util::{hash::hash_bytes, xml::escape},
+};
+use image::imageops::FilterType;
+use pulldown_cmark::{Event, Tag};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// folder in output folder holding resized images
+pub const PROCESSED_IMAGES_FOLDER: &str = "processed_images";
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResizeOp {
+    /// exactly `width` x `height`, aspect ratio is not kept
+    Scale,
+    /// `width` wide with aspect ratio kept
+    FitWidth,
+    /// `height` high with aspect ratio kept
+    FitHeight,
+    /// fit into `width` x `height` with aspect ratio kept, smaller image is not enlarged
+    Fit,
+    /// cover `width` x `height` with aspect ratio kept and crop the overflow from center
+    Fill,
+}
+
+/// resized image which is generated into output folder after pages are rendered
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProcessedImage {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// resize job, those requested by a page are kept in its cache entry and registered again
+/// once the page is skipped
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ImageJob {
+    source: PathBuf,
+    op: ResizeOp,
+    width: u32,
+    height: u32,
+}
+
+/// jobs of output files
+pub type ImageJobs = BTreeMap<String, ImageJob>;
+
+thread_local! {
+    /// jobs registered by what is running in `record` on current thread
+    static RECORDED: RefCell<Option<ImageJobs>> = const { RefCell::new(None) };
+}
+
+/// run `f` and return along with resize jobs it registers, `f` must not hand work over to
+/// other threads
+pub fn record<T>(f: impl FnOnce() -> T) -> (T, ImageJobs) {
+    let outer = RECORDED.with(|recorded| recorded.replace(Some(ImageJobs::new())));
+    let result = f();
+    let jobs = RECORDED.with(|recorded| recorded.replace(outer));
+    (result, jobs.unwrap_or_default())
+}
+
+/// whether output files of jobs are still what their source files would produce
+pub fn is_up_to_date(jobs: &ImageJobs) -> bool {
+    jobs.iter()
+        .all(|(output, job)| job.output().ok().as_deref() == Some(output.as_str()))
+}
+
+/// resolves images of site and collects resize jobs requested while loading and rendering pages
+#[derive(Debug)]
+pub struct ImageProcessor {
+    working_path: PathBuf,
+    site: Site,
+    /// output path -> source path of statics
+    statics: Vec<(String, PathBuf)>,
+    /// output file in output folder -> job
+    jobs: Mutex<BTreeMap<String, ImageJob>>,
+}
+
+impl ImageProcessor {
+    pub fn new(path: impl AsRef<Path>, config: &Config) -> Self {
+        let working_path = path.as_ref().to_path_buf();
+        let mut statics: Vec<(String, PathBuf)> = config
+            .statics
+            .iter()
+            .map(|statics| {
+                let to = statics.to.trim_start_matches('/').to_string();
+                (to, working_path.join(statics.base()))
+            })
+            .collect();
+        let theme_statics = working_path
+            .join("templates")
+            .join(&config.site.theme)
+            .join("statics");
+        statics.push(("statics".to_string(), theme_statics));
+        ImageProcessor {
+            working_path,
+            site: Site {
+                domain: config.site.domain.clone(),
+                domain_root: config.site.domain_root.clone(),
+                ..Default::default()
+            },
+            statics,
+            jobs: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// source file of image, `path` is either a file path relative to working folder or an url
+    /// of site's statics like `/statics/a.png`
+    pub fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let file = self.working_path.join(path.trim_start_matches('/'));
+        if !path.starts_with('/') && file.is_file() {
+            return Some(file);
+        }
+        self.resolve_url(path)
+    }
+
+    fn resolve_url(&self, url: &str) -> Option<PathBuf> {
+        let domain = self.site.domain.trim_end_matches('/');
+        let url = match url.strip_prefix(domain) {
+            Some(path) if !domain.is_empty() => path,
+            _ if url.contains("://") || url.starts_with("//") => return None,
+            _ => url,
+        };
+        let url = url.split(['?', '#']).next().unwrap_or_default();
+        let root = self.site.root_url("");
+        let path = url.strip_prefix(root.as_str())?;
+        let path = percent_encoding::percent_decode_str(path)
+            .decode_utf8()
+            .ok()?;
+        self.statics.iter().find_map(|(to, from)| {
+            let file = if path == to.as_str() {
+                from.clone()
+            } else {
+                from.join(path.strip_prefix(to.as_str())?.strip_prefix('/')?)
+            };
+            Some(file).filter(|file| file.is_file())
+        })
+    }
+
+    /// register a resize job and return the image it will produce
+    pub fn resize(
+        &self,
+        source: &Path,
+        op: ResizeOp,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> Result<ProcessedImage, StapleError> {
+        let error = |reason: String| StapleError::ImageError {
+            path: source.to_string_lossy().to_string(),
+            reason,
+        };
+        let original = image::image_dimensions(source).map_err(|e| error(e.to_string()))?;
+        let (width, height) = target_size(original, op, width, height).map_err(error)?;
+
+        let job = ImageJob {
+            source: source.to_path_buf(),
+            op,
+            width,
+            height,
+        };
+        let output = job.output()?;
+        let url = self.site.root_url(&output);
+        RECORDED.with(|recorded| {
+            if let Some(recorded) = recorded.borrow_mut().as_mut() {
+                recorded.insert(output.clone(), job.clone());
+            }
+        });
+        self.jobs.lock().expect("poisoned lock").insert(output, job);
+        Ok(ProcessedImage { url, width, height })
+    }
+
+    /// register jobs again for page skipped by build cache
+    pub fn register(&self, jobs: &ImageJobs) {
+        let mut registered = self.jobs.lock().expect("poisoned lock");
+        for (output, job) in jobs {
+            registered.insert(output.clone(), job.clone());
+        }
+    }
+
+    /// output files of registered jobs
+    pub fn outputs(&self) -> Vec<String> {
+        let jobs = self.jobs.lock().expect("poisoned lock");
+        jobs.keys().cloned().collect()
+    }
+
+    /// generate registered images into output folder, pages skipped by build cache register
+    /// their images again so that images no longer used are removed as stale outputs
+    pub fn process(
+        &self,
+        output_folder: impl AsRef<Path>,
+        cache: &BuildCache,
+        current: &mut BuildCache,
+    ) -> Result<(), StapleError> {
+        let output_folder = output_folder.as_ref();
+        let jobs = self.jobs.lock().expect("poisoned lock").clone();
+        let results: Vec<Result<(), StapleError>> = jobs
+            .par_iter()
+            .filter(|(output, _)| {
+                !cache.images.contains_key(*output) || !output_folder.join(output).exists()
+            })
+            .map(|(output, job)| {
+                info!("processing image {}", output);
+                job.run(output_folder.join(output))
+            })
+            .collect();
+        results.into_iter().collect::<Result<(), StapleError>>()?;
+
+        for (output, job) in jobs {
+            current
+                .images
+                .insert(output, job.source.to_string_lossy().to_string());
+        }
+        Ok(())
+    }
+}
+
+impl ImageJob {
+    /// output file named by digest of source file and parameters
+    fn output(&self) -> Result<String, StapleError> {
+        let digest = hash_bytes(
+            [
+                std::fs::read(&self.source)?,
+                format!("{:?}{}x{}", self.op, self.width, self.height).into_bytes(),
+            ]
+            .concat(),
+        );
+        let extension = self
+            .source
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("png")
+            .to_lowercase();
+        Ok(format!(
+            "{}/{}.{}",
+            PROCESSED_IMAGES_FOLDER,
+            &digest[..16],
+            extension
+        ))
+    }
+
+    fn run(&self, target: PathBuf) -> Result<(), StapleError> {
+        let error = |reason: String| StapleError::ImageError {
+            path: self.source.to_string_lossy().to_string(),
+            reason,
+        };
+        let image = image::open(&self.source).map_err(|e| error(e.to_string()))?;
+        let image = match self.op {
+            ResizeOp::Fill => image.resize_to_fill(self.width, self.height, FilterType::Lanczos3),
+            _ => image.resize_exact(self.width, self.height, FilterType::Lanczos3),
+        };
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        image.save(&target).map_err(|e| error(e.to_string()))
+    }
+}
+
+/// size of resized image
+fn target_size(
+    (original_width, original_height): (u32, u32),
+    op: ResizeOp,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Result<(u32, u32), String> {
+    let scale = |value: u32, numerator: u32, denominator: u32| {
+        ((value as f64 * numerator as f64 / denominator as f64).round() as u32).max(1)
+    };
+    let required = |value: Option<u32>, name: &str| match value {
+        Some(0) => Err(format!("{} of `{:?}` must be greater than 0", name, op)),
+        Some(value) => Ok(value),
+        None => Err(format!("`{:?}` requires {}", op, name)),
+    };
+    Ok(match op {
+        ResizeOp::Scale | ResizeOp::Fill => {
+            (required(width, "width")?, required(height, "height")?)
+        }
+        ResizeOp::FitWidth => {
+            let width = required(width, "width")?;
+            (width, scale(original_height, width, original_width))
+        }
+        ResizeOp::FitHeight => {
+            let height = required(height, "height")?;
+            (scale(original_width, height, original_height), height)
+        }
+        ResizeOp::Fit => {
+            let (width, height) = (required(width, "width")?, required(height, "height")?);
+            if original_width <= width && original_height <= height {
+                (original_width, original_height)
+            } else if original_width as u64 * height as u64 > original_height as u64 * width as u64
+            {
+                (width, scale(original_height, width, original_width))
+            } else {
+                (scale(original_width, height, original_height), height)
+            }
+        }
+    })
+}
+
+/// give local images in markdown their size and `srcset` of resized variants, images which cannot
+/// be decoded like svg are kept as they are
+pub fn responsive_images<'a>(
+    events: impl Iterator<Item = Event<'a>>,
+    processor: &ImageProcessor,
+    config: &ResponsiveImages,
+    bundle: Option<&Bundle>,
+) -> Result<Vec<Event<'a>>, StapleError> {
+    let mut output = vec![];
+    let mut image: Option<(Vec<Event<'a>>, String)> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::Image(..)) if image.is_none() => {
+                image = Some((vec![event], String::new()));
+            }
+            Event::End(Tag::Image(link_type, url, title)) if image.is_some() => {
+                let (mut events, alt) = image.take().expect("image is missing");
+                let source = bundle
+                    .and_then(|bundle| bundle.resolve_url(&url))
+                    .or_else(|| processor.resolve_url(&url));
+                let html = match source {
+                    Some(source) => {
+                        responsive_image(processor, config, &source, &url, &alt, &title)?
+                    }
+                    None => None,
+                };
+                match html {
+                    Some(html) => output.push(Event::Html(html.into())),
+                    None => {
+                        events.push(Event::End(Tag::Image(link_type, url, title)));
+                        output.extend(events);
+                    }
+                }
+            }
+            other => match image.as_mut() {
+                Some((events, alt)) => {
+                    if let Event::Text(text) | Event::Code(text) = &other {
+                        alt.push_str(text);
+                    }
+                    events.push(other);
+                }
+                None => output.push(other),
+            },
+        }
+    }
+    Ok(output)
+}
+
+fn responsive_image(
+    processor: &ImageProcessor,
+    config: &ResponsiveImages,
+    source: &Path,
+    url: &str,
+    alt: &str,
+    title: &str,
+) -> Result<Option<String>, StapleError> {
+    let (width, height) = match image::image_dimensions(source) {
+        Ok(dimensions) => dimensions,
+        Err(e) => {
+            warn!("cannot read size of image {}: {}", source.display(), e);
+            return Ok(None);
+        }
+    };
+    let mut srcset = vec![];
+    for variant in config.widths.iter().filter(|variant| **variant < width) {
+        let resized = processor.resize(source, ResizeOp::FitWidth, Some(*variant), None)?;
+        srcset.push(format!("{} {}w", resized.url, resized.width));
+    }
+    let mut html = format!(r#"<img src="{}" alt="{}""#, escape(url), escape(alt));
+    if !title.is_empty() {
+        html.push_str(&format!(r#" title="{}""#, escape(title)));
+    }
+    html.push_str(&format!(r#" width="{}" height="{}""#, width, height));
+    if !srcset.is_empty() {
+        srcset.push(format!("{} {}w", url, width));
+        html.push_str(&format!(r#" srcset="{}""#, escape(&srcset.join(", "))));
+    }
+    html.push_str(" />");
+    Ok(Some(html))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cache::BuildCache,
+        command::{
+            add::{add, AddOptions},
+            build::build,
+        },
+        config::{Config, ResponsiveImages},
+        images::{target_size, ImageProcessor, ResizeOp},
+        test::setup,
+    };
+    use pulldown_cmark::{html::push_html, Parser};
+
+    #[test]
+    fn should_compute_target_size() {
+        assert_eq!(
+            Ok((50, 25)),
+            target_size((200, 100), ResizeOp::FitWidth, Some(50), None)
+        );
+        assert_eq!(
+            Ok((100, 50)),
+            target_size((200, 100), ResizeOp::FitHeight, None, Some(50))
+        );
+        assert_eq!(
+            Ok((60, 30)),
+            target_size((200, 100), ResizeOp::Fit, Some(60), Some(60))
+        );
+        assert_eq!(
+            Ok((200, 100)),
+            target_size((200, 100), ResizeOp::Fit, Some(400), Some(400))
+        );
+        assert_eq!(
+            Ok((30, 30)),
+            target_size((200, 100), ResizeOp::Fill, Some(30), Some(30))
+        );
+        assert!(target_size((200, 100), ResizeOp::Scale, Some(30), None).is_err());
+    }
+
+    #[test]
+    fn should_resize_images_and_emit_srcset() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        std::fs::create_dir_all(dir.join("templates/staple/statics"))?;
+        image::RgbImage::new(200, 100).save(dir.join("templates/staple/statics/a.png"))?;
+        let processor = ImageProcessor::new(&dir, &Config::default());
+        let config = ResponsiveImages {
+            widths: vec![50, 400],
+        };
+
+        std::fs::write(dir.join("templates/staple/statics/b.svg"), "<svg></svg>")?;
+        let parser = Parser::new(
+            "![an *image*](/statics/a.png \"t\") ![x](https://a.com/b.png) ![svg](/statics/b.svg)",
+        );
+        let events = super::responsive_images(parser, &processor, &config, None)?;
+        let mut html = String::new();
+        push_html(&mut html, events.into_iter());
+        let output = processor.outputs().remove(0);
+        assert_eq!(
+            format!(
+                "<p><img src=\"/statics/a.png\" alt=\"an image\" title=\"t\" width=\"200\" height=\"100\" srcset=\"/{} 50w, /statics/a.png 200w\" /> <img src=\"https://a.com/b.png\" alt=\"x\" /> <img src=\"/statics/b.svg\" alt=\"svg\" /></p>\n",
+                output
+            ),
+            html
+        );
+
+        let resized = processor.resize(
+            &processor.resolve("templates/staple/statics/a.png").unwrap(),
+            ResizeOp::Fill,
+            Some(30),
+            Some(40),
+        )?;
+        assert_eq!((30, 40), (resized.width, resized.height));
+
+        let mut current = BuildCache::default();
+        processor.process(dir.join("public"), &BuildCache::default(), &mut current)?;
+        assert_eq!(2, current.images.len());
+        let generated = image::open(dir.join("public").join(&resized.url[1..]))?;
+        assert_eq!((30, 40), image::GenericImageView::dimensions(&generated));
+        Ok(())
+    }
+
+    #[test]
+    fn should_resize_image_in_template() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        std::fs::create_dir_all(dir.join("templates/staple/statics"))?;
+        image::RgbImage::new(200, 100).save(dir.join("templates/staple/statics/a.png"))?;
+        std::fs::write(
+            dir.join("templates/staple/article.html"),
+            r#"{% set image = resize_image(path="/statics/a.png", width=20, op="fit_width") %}{{ image.width }}x{{ image.height }} {{ image.url | safe }}"#,
+        )?;
+        let options = AddOptions {
+            title: "one".to_owned(),
+            url: None,
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(&dir, options)?;
+        build(&dir, false, None)?;
+
+        let output = std::fs::read_to_string(dir.join("public/one/index.html"))?;
+        let (size, url) = output.split_at(6);
+        assert_eq!("20x10 ", size);
+        let resized = dir.join("public").join(&url[1..]);
+        assert!(resized.exists());
+
+        build(&dir, false, None)?;
+        assert!(resized.exists());
+
+        image::RgbImage::new(100, 100).save(dir.join("templates/staple/statics/a.png"))?;
+        build(&dir, false, None)?;
+        let output = std::fs::read_to_string(dir.join("public/one/index.html"))?;
+        let (size, url) = output.split_at(6);
+        assert_eq!("20x20 ", size);
+        assert!(!resized.exists());
+        let resized = dir.join("public").join(&url[1..]);
+        assert!(resized.exists());
+
+        std::fs::write(dir.join("templates/staple/article.html"), "")?;
+        build(&dir, false, None)?;
+        assert!(!resized.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{config::Site, error::StapleError};
+use once_cell::sync::Lazy;
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+use url::Url;
+use walkdir::WalkDir;
+
+static HREF_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\shref\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+))"#).expect("invalid regex")
+});
+static ANCHOR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\s(?:id|name)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+))"#)
+        .expect("invalid regex")
+});
+
+/// host used to resolve links, links resolved to other hosts are external and not checked
+const LOCAL_ORIGIN: &str = "http://staple.local";
+
+/// generated files of site, paths are relative to output folder like `a/index.html`
+#[derive(Debug, Default)]
+pub struct LinkChecker {
+    files: HashSet<String>,
+    anchors: HashMap<String, HashSet<String>>,
+    links: Vec<(String, String)>,
+}
+
+impl LinkChecker {
+    pub fn from_folder(folder: impl AsRef<Path>) -> Result<Self, StapleError> {
+        let mut checker = LinkChecker::default();
+        let files = WalkDir::new(&folder)
+            .sort_by(|one, other| one.file_name().cmp(other.file_name()))
+            .into_iter()
+            .flat_map(|e| e.ok())
+            .filter(|de| de.path().is_file());
+        for file in files {
+            let relative = file
+                .path()
+                .strip_prefix(&folder)
+                .expect("file is not in output folder")
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            match file.path().extension().and_then(|e| e.to_str()) {
+                Some("html") | Some("htm") => {
+                    let html = std::fs::read_to_string(file.path())?;
+                    checker.add_page(&relative, &html);
+                }
+                _ => checker.add_file(&relative),
+            }
+        }
+        Ok(checker)
+    }
+
+    pub fn add_file(&mut self, file: &str) {
+        self.files.insert(file.trim_start_matches('/').to_string());
+    }
+
+    /// register html page with its anchors and links
+    pub fn add_page(&mut self, file: &str, html: &str) {
+        let file = file.trim_start_matches('/').to_string();
+        let anchors = captures(&ANCHOR_REGEX, html).collect();
+        self.links
+            .extend(captures(&HREF_REGEX, html).map(|link| (file.clone(), link)));
+        self.anchors.insert(file.clone(), anchors);
+        self.files.insert(file);
+    }
+
+    /// broken links with the page they come from
+    pub fn check(&self, site: &Site) -> Vec<(String, StapleError)> {
+        self.links
+            .iter()
+            .filter_map(|(page, link)| {
+                self.resolve(site, page, link).err().map(|reason| {
+                    let error = StapleError::BrokenLink {
+                        link: link.clone(),
+                        reason,
+                    };
+                    (page.clone(), error)
+                })
+            })
+            .collect()
+    }
+
+    fn resolve(&self, site: &Site, page: &str, link: &str) -> Result<(), String> {
+        let root = match site.domain_root.trim_matches('/') {
+            "" => "/".to_string(),
+            root => format!("/{}/", root),
+        };
+        let origin = Url::parse(LOCAL_ORIGIN).expect("invalid origin");
+        let base = origin
+            .join(&root)
+            .and_then(|root| root.join(page))
+            .map_err(|e| e.to_string())?;
+
+        let domain = site.domain.trim_end_matches('/');
+        let link = match link.strip_prefix(domain) {
+            Some(path) if !domain.is_empty() && (path.is_empty() || path.starts_with('/')) => {
+                format!("/{}", path.trim_start_matches('/'))
+            }
+            _ => link.to_string(),
+        };
+        let url = base.join(&link).map_err(|e| e.to_string())?;
+        if url.host_str() != origin.host_str() || url.scheme() != origin.scheme() {
+            return Ok(());
+        }
+
+        let path = percent_decode_str(url.path()).decode_utf8_lossy();
+        let path = match path.strip_prefix(&root) {
+            Some(path) => path.to_string(),
+            None if format!("{}/", path) == root => String::new(),
+            None => return Err(format!("link is outside of domain root `{}`", root)),
+        };
+        let file = if path.is_empty() || path.ends_with('/') {
+            format!("{}index.html", path)
+        } else if self.files.contains(&path) {
+            path
+        } else {
+            format!("{}/index.html", path)
+        };
+        if !self.files.contains(&file) {
+            return Err(format!("`{}` is not generated", file));
+        }
+
+        if let Some(fragment) = url.fragment() {
+            let fragment = percent_decode_str(fragment).decode_utf8_lossy();
+            let anchors = self.anchors.get(&file);
+            let is_missing = anchors
+                .map(|anchors| !anchors.contains(fragment.as_ref()))
+                .unwrap_or(false);
+            if !fragment.is_empty() && fragment != "top" && is_missing {
+                return Err(format!(
+                    "anchor `#{}` does not exist in `{}`",
+                    fragment, file
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn captures<'a>(regex: &'a Regex, html: &'a str) -> impl Iterator<Item = String> + 'a {
+    regex.captures_iter(html).filter_map(|captures| {
+        captures
+            .get(1)
+            .or_else(|| captures.get(2))
+            .or_else(|| captures.get(3))
+            .map(|value| value.as_str().replace("&amp;", "&"))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{config::Site, link_checker::LinkChecker};
+
+    fn checker() -> LinkChecker {
+        let mut checker = LinkChecker::default();
+        checker.add_page(
+            "index.html",
+            r##"<a href="/a/">a</a><a href="a">a</a><a href="b.html#x">b</a><a href='#top'>top</a>
+            <a href="https://example.com/a/">self</a><a href="https://other.com/x">x</a>
+            <a href="mailto:a@b.c">mail</a><a href="/%E4%B8%AD%E6%96%87/">cjk</a><a href="/rss.xml?x=1">rss</a>"##,
+        );
+        checker.add_page(
+            "a/index.html",
+            r##"<a href="../b.html">b</a><a href="#nope">n</a>"##,
+        );
+        checker.add_page("b.html", r#"<h1 id="x">x</h1><a href="/missing/">m</a>"#);
+        checker.add_page("中文/index.html", "");
+        checker.add_file("rss.xml");
+        checker
+    }
+
+    #[test]
+    fn should_find_broken_links() {
+        let site = Site {
+            domain: "https://example.com".to_string(),
+            ..Default::default()
+        };
+        let broken: Vec<(String, String)> = checker()
+            .check(&site)
+            .into_iter()
+            .map(|(page, e)| (page, e.to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "a/index.html".to_string(),
+                    "broken link `#nope`: anchor `#nope` does not exist in `a/index.html`"
+                        .to_string()
+                ),
+                (
+                    "b.html".to_string(),
+                    "broken link `/missing/`: `missing/index.html` is not generated".to_string()
+                ),
+            ],
+            broken
+        );
+    }
+
+    #[test]
+    fn should_honor_domain_root() {
+        let site = Site {
+            domain_root: "blog".to_string(),
+            ..Default::default()
+        };
+        let mut checker = LinkChecker::default();
+        checker.add_page(
+            "index.html",
+            r#"<a href="/blog/a/">a</a><a href="/blog">root</a><a href="/a/">outside</a>"#,
+        );
+        checker.add_page("a/index.html", "");
+        let broken = checker.check(&site);
+        assert_eq!(1, broken.len());
+        assert!(broken[0].1.to_string().contains("outside of domain root"));
+    }
+}
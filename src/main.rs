@@ -11,11 +11,21 @@ use std::{io::Write, process::exit};
 use structopt::StructOpt;
 
 mod app;
+mod assets;
+mod cache;
 mod command;
 mod config;
 mod constants;
 mod error;
+mod feed;
+mod images;
+mod link_checker;
+mod minify;
+mod paginator;
+mod sass;
 mod server;
+mod sitemap;
+mod taxonomy;
 mod template;
 mod util;
 
@@ -0,0 +1,322 @@
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+use minify_html::Cfg;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::path::Path;
+
+static SCRIPT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)(<script\b[^>]*>)(.*?)(</script\s*>)"#).expect("invalid regex")
+});
+static TYPE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\stype\s*=\s*["']?([^"'\s>]*)"#).expect("invalid regex"));
+
+/// punctuators around which whitespace is dropped, `.` and `/` are not here since `1 .x` and
+/// `a / /b/` need the whitespace
+const PUNCTUATORS: &str = "{}()[];,:=?!&|*%^~<>+-";
+
+/// keywords after which `/` starts a regular expression rather than a division
+const KEYWORDS_BEFORE_EXPRESSION: [&str; 14] = [
+    "return",
+    "typeof",
+    "instanceof",
+    "case",
+    "do",
+    "else",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "yield",
+    "await",
+];
+
+/// html with whitespace and comments removed, inline `<style>` and `<script>` are minified as well
+pub fn html(content: &str) -> String {
+    let content = SCRIPT_REGEX.replace_all(content, |captures: &Captures| {
+        let open = &captures[1];
+        let is_js = match TYPE_REGEX.captures(open) {
+            Some(script_type) => matches!(
+                script_type[1].to_lowercase().as_str(),
+                "" | "module" | "text/javascript" | "application/javascript"
+            ),
+            None => true,
+        };
+        match js(&captures[2]).filter(|_| is_js) {
+            Some(script) => format!("{}{}{}", open, script, &captures[3]),
+            None => captures[0].to_string(),
+        }
+    });
+    // scripts are minified above, minifier of html rewrites code in ways that may change semantics
+    let cfg = Cfg {
+        minify_css: true,
+        minify_js: false,
+        ..Cfg::spec_compliant()
+    };
+    let output = minify_html::minify(content.as_bytes(), &cfg);
+    String::from_utf8(output).unwrap_or_else(|_| content.to_string())
+}
+
+/// minified css, `None` if it cannot be parsed
+pub fn css(content: &str) -> Option<String> {
+    let mut stylesheet = StyleSheet::parse(content, ParserOptions::default()).ok()?;
+    stylesheet.minify(MinifyOptions::default()).ok()?;
+    let options = PrinterOptions {
+        minify: true,
+        ..Default::default()
+    };
+    Some(stylesheet.to_css(options).ok()?.code)
+}
+
+/// javascript without comments and redundant whitespace. code itself is never rewritten and line
+/// breaks between tokens are kept for automatic semicolon insertion.
+/// `None` if a string, comment, template or regular expression is not terminated, or if `/`
+/// after `)` or `}` may start either a division or a regular expression.
+pub fn js(content: &str) -> Option<String> {
+    let chars: Vec<char> = content.chars().collect();
+    let mut output = String::new();
+    // whitespace skipped since last token, `true` if it has line break
+    let mut gap: Option<bool> = None;
+    // depth of braces where each `${` of templates is opened
+    let mut templates: Vec<usize> = vec![];
+    let mut braces = 0;
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        let next = chars.get(index + 1).copied();
+        if c.is_whitespace() {
+            let is_line_break = c == '\n' || c == '\r' || c == '\u{2028}' || c == '\u{2029}';
+            gap = Some(is_line_break || gap == Some(true));
+            index += 1;
+            continue;
+        }
+        if c == '/' && next == Some('/') {
+            while index < chars.len() && chars[index] != '\n' && chars[index] != '\r' {
+                index += 1;
+            }
+            continue;
+        }
+        if c == '/' && next == Some('*') {
+            let end = (index + 2..chars.len().saturating_sub(1))
+                .find(|end| chars[*end] == '*' && chars[*end + 1] == '/')?;
+            let has_line_break = chars[index..end].iter().any(|c| *c == '\n' || *c == '\r');
+            gap = Some(has_line_break || gap == Some(true));
+            index = end + 2;
+            continue;
+        }
+
+        if let Some(has_line_break) = gap.take() {
+            push_gap(&mut output, has_line_break, c);
+        }
+        match c {
+            '\'' | '"' => index = copy_string(&chars, index, &mut output)?,
+            '`' => {
+                let (end, is_interpolation) = copy_template(&chars, index + 1, &mut output, '`')?;
+                if is_interpolation {
+                    templates.push(braces);
+                }
+                index = end;
+            }
+            '}' if templates.last() == Some(&braces) => {
+                templates.pop();
+                let (end, is_interpolation) = copy_template(&chars, index + 1, &mut output, '}')?;
+                if is_interpolation {
+                    templates.push(braces);
+                }
+                index = end;
+            }
+            '/' if is_regex_allowed(&output)? => index = copy_regex(&chars, index, &mut output)?,
+            _ => {
+                match c {
+                    '{' => braces += 1,
+                    '}' => braces = braces.saturating_sub(1),
+                    _ => {}
+                }
+                output.push(c);
+                index += 1;
+            }
+        }
+    }
+    if templates.is_empty() {
+        Some(output)
+    } else {
+        None
+    }
+}
+
+/// put whitespace between last token and the next one starting with `next` if it is needed
+fn push_gap(output: &mut String, has_line_break: bool, next: char) {
+    let previous = match output.chars().last() {
+        Some(previous) => previous,
+        None => return,
+    };
+    let is_punctuator = |c: char| PUNCTUATORS.contains(c);
+    // `a + +b`, `a - --b` and `a < !--b` need the whitespace
+    let is_ambiguous =
+        "+-".contains(previous) && "+-".contains(next) || previous == '<' && next == '!';
+    if has_line_break {
+        if !("{;,(".contains(previous) || ",;)}".contains(next)) {
+            output.push('\n');
+        }
+    } else if is_ambiguous || !(is_punctuator(previous) || is_punctuator(next)) {
+        output.push(' ');
+    }
+}
+
+fn copy_string(chars: &[char], start: usize, output: &mut String) -> Option<usize> {
+    let quote = chars[start];
+    output.push(quote);
+    let mut index = start + 1;
+    while index < chars.len() {
+        let c = chars[index];
+        output.push(c);
+        match c {
+            '\\' => {
+                output.push(*chars.get(index + 1)?);
+                index += 2;
+                continue;
+            }
+            '\n' | '\r' => return None,
+            c if c == quote => return Some(index + 1),
+            _ => {}
+        }
+        index += 1;
+    }
+    None
+}
+
+/// copy template after its opening `` ` `` or `}` until the closing `` ` `` or the next `${`,
+/// returns end position and whether it ends with `${`
+fn copy_template(
+    chars: &[char],
+    start: usize,
+    output: &mut String,
+    open: char,
+) -> Option<(usize, bool)> {
+    output.push(open);
+    let mut index = start;
+    while index < chars.len() {
+        let c = chars[index];
+        output.push(c);
+        match c {
+            '\\' => {
+                output.push(*chars.get(index + 1)?);
+                index += 2;
+                continue;
+            }
+            '`' => return Some((index + 1, false)),
+            '$' if chars.get(index + 1) == Some(&'{') => {
+                output.push('{');
+                return Some((index + 2, true));
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    None
+}
+
+fn copy_regex(chars: &[char], start: usize, output: &mut String) -> Option<usize> {
+    output.push('/');
+    let mut index = start + 1;
+    let mut in_class = false;
+    while index < chars.len() {
+        let c = chars[index];
+        output.push(c);
+        match c {
+            '\\' => {
+                output.push(*chars.get(index + 1)?);
+                index += 2;
+                continue;
+            }
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => return Some(index + 1),
+            '\n' | '\r' => return None,
+            _ => {}
+        }
+        index += 1;
+    }
+    None
+}
+
+/// whether `/` after output starts a regular expression, judged by the token before it.
+/// `None` after `)` and `}` which need the whole syntax tree, like `if (a) /b/.test(c)`
+fn is_regex_allowed(output: &str) -> Option<bool> {
+    let output = output.trim_end();
+    Some(match output.chars().last() {
+        None => true,
+        Some(')') | Some('}') => return None,
+        Some(c) if "(,=:[!&|?{;+-*%<>~^".contains(c) => true,
+        Some(c) if c.is_alphanumeric() || c == '_' || c == '$' => {
+            let word: String = output
+                .chars()
+                .rev()
+                .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .collect();
+            let before = output[..output.len() - word.len()].chars().last();
+            before != Some('.') && KEYWORDS_BEFORE_EXPRESSION.contains(&word.as_str())
+        }
+        _ => false,
+    })
+}
+
+/// whether output file is a html page
+pub fn is_html(file: &str) -> bool {
+    matches!(
+        Path::new(file).extension().and_then(|e| e.to_str()),
+        Some("html") | Some("htm")
+    )
+}
+
+/// minified content of statics file by its extension, `None` if it is not css or javascript
+/// or cannot be minified, in which case the file is copied as it is
+pub fn statics(file: &Path) -> Option<String> {
+    let minify: fn(&str) -> Option<String> = match file.extension().and_then(|e| e.to_str()) {
+        Some("css") => css,
+        Some("js") | Some("mjs") => js,
+        _ => return None,
+    };
+    let content = std::fs::read_to_string(file).ok()?;
+    let minified = minify(&content);
+    if minified.is_none() {
+        warn!("cannot minify {}, it is copied as it is", file.display());
+    }
+    minified
+}
+
+#[cfg(test)]
+mod test {
+    use crate::minify::js;
+
+    #[test]
+    fn should_strip_comments_and_whitespace_of_js() {
+        assert_eq!(
+            Some("function add(a,b){return a+b}\nadd(1,2)".to_string()),
+            js("// add\nfunction add(a, b) {\n  /* sum */\n  return a + b\n}\n\nadd(1, 2)\n")
+        );
+        assert_eq!(
+            Some("let s='a  // b';let r=/ [/]  x/g;let t=`x  ${{a:1}.a+`  y`}  z`".to_string()),
+            js("let s = 'a  // b';\nlet r = / [/]  x/g;\nlet t = `x  ${ {a: 1}.a + `  y` }  z`")
+        );
+        assert_eq!(
+            Some("a+ +b;c- --d;x=1 .toString();y=a / b / c".to_string()),
+            js("a + +b; c - --d; x = 1 .toString(); y = a / b / c")
+        );
+        assert_eq!(
+            Some("return\nx".to_string()),
+            js("return   // nothing\n  x")
+        );
+        assert_eq!(None, js("let s = 'unterminated"));
+        assert_eq!(None, js("if (ok) /a  +b/.test(s)"));
+        assert_eq!(None, js("x = (a) / b"));
+        assert_eq!(
+            Some("x=a[0]/ b;if(ok)y=1".to_string()),
+            js("x = a[0] / b; if (ok) y = 1")
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    data::PageInfo,
+    error::StapleError,
+    util::filter::{get_json_pointer, is_field_false},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// options declared in page's data `paginate` field, like `{"size": 10, "exclude": "data.page"}`
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PaginateOptions {
+    /// count of items in each page
+    pub size: usize,
+    /// exclude pages whose field is set and not false, e.g. `data.page`
+    pub exclude: Option<String>,
+    /// field to sort items by, e.g. `title` or `data.order`, default is the order of `pages`
+    pub sort: Option<String>,
+    pub reverse: bool,
+}
+
+impl Default for PaginateOptions {
+    fn default() -> Self {
+        Self {
+            size: 10,
+            exclude: None,
+            sort: None,
+            reverse: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Paginator<'a> {
+    /// page number starts from 1
+    pub current: usize,
+    pub total: usize,
+    pub size: usize,
+    pub url: String,
+    pub first: String,
+    pub last: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    pub items: Vec<&'a PageInfo>,
+}
+
+impl PaginateOptions {
+    pub fn from_page(article: &PageInfo) -> Result<Option<Self>, StapleError> {
+        match article.data.get("paginate") {
+            Some(value) => {
+                let options: PaginateOptions = serde_json::from_value(value.clone())?;
+                if options.size == 0 {
+                    return Err(StapleError::ArticleError {
+                        filename: article.file.clone(),
+                        reason: "size of paginate must be greater than 0".to_string(),
+                    });
+                }
+                Ok(Some(options))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// url of n-th page, the first page keeps page's own url and others are `<url>/page/<n>/`
+pub fn page_url(url: &str, number: usize) -> String {
+    if number == 1 {
+        url.to_string()
+    } else {
+        format!("{}/page/{}/", url.trim_end_matches('/'), number)
+    }
+}
+
+/// split pages into paginators for article, article itself is never listed.
+pub fn paginate<'a>(
+    article: &PageInfo,
+    options: &PaginateOptions,
+    pages: &[&'a PageInfo],
+) -> Result<Vec<Paginator<'a>>, StapleError> {
+    let mut items = vec![];
+    for page in pages
+        .iter()
+        .copied()
+        .filter(|page| page.file != article.file)
+    {
+        let value = serde_json::to_value(page)?;
+        if let Some(key) = &options.exclude {
+            if !is_field_false(&value, key) {
+                continue;
+            }
+        }
+        items.push((page, value));
+    }
+    if let Some(key) = &options.sort {
+        let pointer = get_json_pointer(key);
+        items.sort_by(|(one, one_value), (other, other_value)| {
+            if key == "datetime" {
+                return one.datetime.cmp(&other.datetime);
+            }
+            compare(one_value.pointer(&pointer), other_value.pointer(&pointer))
+        });
+    }
+    if options.reverse {
+        items.reverse();
+    }
+
+    let chunks: Vec<Vec<&PageInfo>> = items
+        .chunks(options.size)
+        .map(|chunk| chunk.iter().map(|(page, _)| *page).collect())
+        .collect();
+    let total = chunks.len().max(1);
+    let mut chunks = chunks.into_iter();
+    Ok((1..=total)
+        .map(|current| Paginator {
+            current,
+            total,
+            size: options.size,
+            url: page_url(&article.url, current),
+            first: page_url(&article.url, 1),
+            last: page_url(&article.url, total),
+            prev: if current > 1 {
+                Some(page_url(&article.url, current - 1))
+            } else {
+                None
+            },
+            next: if current < total {
+                Some(page_url(&article.url, current + 1))
+            } else {
+                None
+            },
+            items: chunks.next().unwrap_or_default(),
+        })
+        .collect())
+}
+
+fn compare(one: Option<&Value>, other: Option<&Value>) -> Ordering {
+    match (one, other) {
+        (Some(Value::Number(one)), Some(Value::Number(other))) => one
+            .as_f64()
+            .partial_cmp(&other.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Some(Value::String(one)), Some(Value::String(other))) => one.cmp(other),
+        (Some(Value::Bool(one)), Some(Value::Bool(other))) => one.cmp(other),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        _ => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        command::{
+            add::{add, AddOptions},
+            build::build,
+        },
+        data::PageInfo,
+        paginator::{page_url, paginate, PaginateOptions},
+        test::setup,
+    };
+    use chrono::Utc;
+
+    fn page(file: &str, title: &str) -> PageInfo {
+        PageInfo {
+            file: file.to_string(),
+            url: "/".to_string(),
+            title: title.to_string(),
+            template: "".to_string(),
+            draw: false,
+            datetime: Utc::now().into(),
+            data: Default::default(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_page_url() {
+        assert_eq!("/", page_url("/", 1));
+        assert_eq!("/page/2/", page_url("/", 2));
+        assert_eq!("blog/page/3/", page_url("blog", 3));
+        assert_eq!("/blog/page/3/", page_url("/blog/", 3));
+    }
+
+    #[test]
+    fn should_split_pages_by_size() -> Result<(), Box<dyn std::error::Error>> {
+        let index = page("index", "index");
+        let pages = [
+            page("index", "index"),
+            page("c", "c"),
+            page("a", "a"),
+            page("b", "b"),
+        ];
+        let options = PaginateOptions {
+            size: 2,
+            sort: Some("title".to_string()),
+            ..Default::default()
+        };
+        let paginators = paginate(&index, &options, &pages.iter().collect::<Vec<_>>())?;
+        assert_eq!(2, paginators.len());
+
+        let first = &paginators[0];
+        assert_eq!(1, first.current);
+        assert_eq!(None, first.prev);
+        assert_eq!(Some("/page/2/".to_string()), first.next);
+        let titles: Vec<&str> = first.items.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(vec!["a", "b"], titles);
+
+        let second = &paginators[1];
+        assert_eq!(Some("/".to_string()), second.prev);
+        assert_eq!(None, second.next);
+        assert_eq!("c", second.items[0].title);
+        Ok(())
+    }
+
+    #[test]
+    fn should_always_have_one_page() -> Result<(), Box<dyn std::error::Error>> {
+        let index = page("index", "index");
+        let paginators = paginate(&index, &Default::default(), &[])?;
+        assert_eq!(1, paginators.len());
+        assert!(paginators[0].items.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn should_render_paginated_pages() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        std::fs::write(
+            dir.join("templates/staple/list.html"),
+            "{{ paginator.current }}/{{ paginator.total }}:{% for item in paginator.items %}{{ item.title }}{% endfor %}",
+        )?;
+        for title in &["one", "two", "three"] {
+            let options = AddOptions {
+                title: title.to_string(),
+                url: None,
+                template: None,
+                draw: false,
+                data: false,
+            };
+            add(&dir, options)?;
+        }
+        std::fs::write(
+            dir.join("data/index.json"),
+            r#"{"url": "/", "title": "index", "template": "list.html", "datetime": "2020-01-01T00:00:00+08:00",
+            "data": {"paginate": {"size": 2, "sort": "title"}}, "content": ""}"#,
+        )?;
+        build(&dir, false, None)?;
+
+        assert_eq!(
+            "1/2:onethree",
+            std::fs::read_to_string(dir.join("public/index.html"))?
+        );
+        assert_eq!(
+            "2/2:two",
+            std::fs::read_to_string(dir.join("public/page/2/index.html"))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_list_index_pages_of_sections() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        std::fs::write(
+            dir.join("templates/staple/list.html"),
+            "{% for item in paginator.items %}{{ item.title }},{% endfor %}",
+        )?;
+        std::fs::create_dir_all(dir.join("data/post"))?;
+        std::fs::write(
+            dir.join("data/post/_index.json"),
+            r#"{"url": "/post/", "title": "posts", "template": "article.html", "datetime": "2020-01-01T00:00:00+08:00",
+            "data": {}, "content": ""}"#,
+        )?;
+        std::fs::write(
+            dir.join("data/post/one.json"),
+            r#"{"url": "/post/one", "title": "one", "template": "article.html", "datetime": "2020-01-01T00:00:00+08:00",
+            "data": {}, "content": ""}"#,
+        )?;
+        std::fs::write(
+            dir.join("data/index.json"),
+            r#"{"url": "/", "title": "index", "template": "list.html", "datetime": "2020-01-01T00:00:00+08:00",
+            "data": {"paginate": {"size": 10}}, "content": ""}"#,
+        )?;
+        build(&dir, false, None)?;
+
+        assert_eq!(
+            "one,",
+            std::fs::read_to_string(dir.join("public/index.html"))?
+        );
+        assert!(dir.join("public/post/index.html").exists());
+        Ok(())
+    }
+}
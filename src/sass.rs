@@ -0,0 +1,83 @@
+use crate::{config::Sass, error::StapleError, util::hash::hash_bytes};
+use grass::{Fs, Options, OutputStyle};
+use std::{
+    cell::RefCell,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// whether file is sass source, which is compiled into css instead of being copied
+pub fn is_sass(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("scss") | Some("sass")
+    )
+}
+
+/// partials like `_base.scss` are only imported by other stylesheets
+pub fn is_partial(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    is_sass(path) && name.starts_with('_')
+}
+
+/// output file of stylesheet, like `statics/main.css` of `statics/main.scss`
+pub fn output_file(path: &Path) -> PathBuf {
+    path.with_extension("css")
+}
+
+/// compile stylesheet into css, along with all files it is compiled from
+pub fn compile(file: &Path, config: &Sass) -> Result<(String, Vec<String>), StapleError> {
+    let fs = RecordingFs::default();
+    let style = if config.minify {
+        OutputStyle::Compressed
+    } else {
+        OutputStyle::Expanded
+    };
+    let options = Options::default().fs(&fs).style(style);
+    let css = grass::from_path(file, &options).map_err(|e| StapleError::SassError {
+        file: file.to_string_lossy().to_string(),
+        reason: e.to_string(),
+    })?;
+    let mut sources = fs.files.into_inner();
+    sources.sort();
+    sources.dedup();
+    let sources = sources
+        .iter()
+        .map(|source| source.to_string_lossy().to_string())
+        .collect();
+    Ok((css, sources))
+}
+
+/// digest of files a stylesheet is compiled from, `None` if some of them are gone
+pub fn digest(sources: &[String]) -> Option<String> {
+    let mut content = vec![];
+    for source in sources {
+        content.extend(std::fs::read(source).ok()?);
+        content.extend(source.as_bytes());
+    }
+    Some(hash_bytes(content))
+}
+
+/// file system recording files read by compiler, so that imports and partials are tracked
+#[derive(Debug, Default)]
+struct RecordingFs {
+    files: RefCell<Vec<PathBuf>>,
+}
+
+impl Fs for RecordingFs {
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files.borrow_mut().push(path.to_path_buf());
+        std::fs::read(path)
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    cache::BuildCache,
+    config::Config,
+    constants::PUBLIC_FOLDER,
+    data::{section::is_section_index, PageInfo},
+    error::StapleError,
+    minify,
+    util::xml::escape,
+};
+use itertools::Itertools;
+use serde_json::Value;
+use std::{collections::HashMap, path::Path};
+
+pub const SITEMAP_FILE: &str = "sitemap.xml";
+pub const ROBOTS_FILE: &str = "robots.txt";
+
+/// generate `sitemap.xml` for all rendered html pages, and `robots.txt` pointing to it if user does not
+/// provide one. absolute url is required by sitemap, so nothing is generated without `site.domain`.
+pub fn render(
+    articles: &[PageInfo],
+    config: &Config,
+    path: impl AsRef<Path>,
+    cache: &BuildCache,
+    current: &mut BuildCache,
+) -> Result<(), StapleError> {
+    if config.site.domain.is_empty() {
+        debug!("site.domain is empty, skip generating sitemap");
+        return Ok(());
+    }
+    let output_folder = path.as_ref().join(PUBLIC_FOLDER);
+    info!("generating {}", SITEMAP_FILE);
+    current.write(
+        cache,
+        &output_folder,
+        SITEMAP_FILE,
+        render_sitemap(config, articles, current),
+    )?;
+
+    let is_user_provided =
+        current.pages.contains_key(ROBOTS_FILE) || current.statics.contains_key(ROBOTS_FILE);
+    if !is_user_provided {
+        info!("generating {}", ROBOTS_FILE);
+        let robots = format!(
+            "User-agent: *\nDisallow:\n\nSitemap: {}\n",
+            config.site.absolute_url(SITEMAP_FILE)
+        );
+        current.write(cache, &output_folder, ROBOTS_FILE, robots)?;
+    }
+    Ok(())
+}
+
+/// html outputs rendered in current build, from pages including their paginated and extra
+/// outputs, and taxonomies. index pages of sections are left out.
+/// pages can be configured via `sitemap` field in data, `false` to exclude page from sitemap,
+/// or an object like `{"priority": 0.8, "changefreq": "weekly"}`.
+pub fn render_sitemap(config: &Config, articles: &[PageInfo], current: &BuildCache) -> String {
+    let sources: HashMap<&str, &PageInfo> = articles
+        .iter()
+        .map(|article| (article.file.as_str(), article))
+        .collect();
+    let pages = current
+        .pages
+        .iter()
+        .map(|(output_file, page)| (output_file, sources.get(page.source.as_str()).copied()))
+        .filter(|(_, article)| match article {
+            Some(article) => !is_section_index(&article.file),
+            None => true,
+        });
+    let generated = current
+        .generated
+        .keys()
+        .map(|output_file| (output_file, None));
+    let outputs = pages
+        .chain(generated)
+        .filter(|(output_file, _)| minify::is_html(output_file))
+        .sorted_by(|(one, _), (other, _)| one.cmp(other));
+
+    let mut output = String::new();
+    output.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    output.push_str(r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+    for (output_file, article) in outputs {
+        let setting = article.and_then(|article| article.data.get("sitemap"));
+        if let Some(Value::Bool(false)) = setting {
+            continue;
+        }
+        let url = output_file
+            .strip_suffix("index.html")
+            .unwrap_or(output_file);
+        output.push_str("<url>");
+        output.push_str(&format!(
+            "<loc>{}</loc>",
+            escape(&config.site.absolute_url(url))
+        ));
+        if let Some(article) = article {
+            output.push_str(&format!(
+                "<lastmod>{}</lastmod>",
+                article.datetime.to_rfc3339()
+            ));
+        }
+        if let Some(setting) = setting {
+            if let Some(changefreq) = setting.get("changefreq").and_then(Value::as_str) {
+                output.push_str(&format!("<changefreq>{}</changefreq>", escape(changefreq)));
+            }
+            if let Some(priority) = setting.get("priority").and_then(Value::as_f64) {
+                output.push_str(&format!("<priority>{:.1}</priority>", priority));
+            }
+        }
+        output.push_str("</url>");
+    }
+    output.push_str("</urlset>");
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cache::{BuildCache, PageCache},
+        command::{
+            add::{add, AddOptions},
+            build::build,
+        },
+        config::Config,
+        data::PageInfo,
+        sitemap::render_sitemap,
+        test::setup,
+    };
+    use chrono::DateTime;
+
+    fn page(url: &str, sitemap: Option<serde_json::Value>) -> PageInfo {
+        let mut data = std::collections::HashMap::new();
+        if let Some(value) = sitemap {
+            data.insert("sitemap".to_string(), value);
+        }
+        PageInfo {
+            file: format!("data{}.md", url),
+            url: url.to_string(),
+            title: "".to_string(),
+            template: "".to_string(),
+            draw: false,
+            datetime: DateTime::parse_from_rfc3339("2020-01-02T03:04:05+08:00").unwrap(),
+            data,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn should_render_sitemap_with_page_settings() {
+        let mut config = Config::default();
+        config.site.domain = "https://example.com".to_string();
+        let pages = [
+            page("/", None),
+            page(
+                "/a",
+                Some(serde_json::json!({"priority": 0.8, "changefreq": "weekly"})),
+            ),
+            page("/b", Some(serde_json::Value::Bool(false))),
+            page("/rss.xml", None),
+            page("/post/_index", None),
+        ];
+        let mut current = BuildCache::default();
+        let outputs = [
+            (0, "index.html"),
+            (0, "page/2/index.html"),
+            (1, "a/index.html"),
+            (1, "a/index.amp.html"),
+            (2, "b/index.html"),
+            (3, "rss.xml"),
+            (4, "post/index.html"),
+        ];
+        for (index, output_file) in &outputs {
+            let page = PageCache {
+                source: pages[*index].file.clone(),
+                key: "".to_string(),
+                output: "".to_string(),
+                images: Default::default(),
+            };
+            current.pages.insert(output_file.to_string(), page);
+        }
+        for output_file in &["tags/index.html", "tags/rust/index.html", "atom.xml"] {
+            current
+                .generated
+                .insert(output_file.to_string(), "".to_string());
+        }
+
+        let sitemap = render_sitemap(&config, &pages, &current);
+        assert!(sitemap.contains(
+            "<url><loc>https://example.com/</loc><lastmod>2020-01-02T03:04:05+08:00</lastmod></url>"
+        ));
+        assert!(sitemap.contains(
+            "<url><loc>https://example.com/page/2/</loc><lastmod>2020-01-02T03:04:05+08:00</lastmod></url>"
+        ));
+        assert!(sitemap.contains(
+            "<loc>https://example.com/a/</loc><lastmod>2020-01-02T03:04:05+08:00</lastmod><changefreq>weekly</changefreq><priority>0.8</priority>"
+        ));
+        assert!(sitemap.contains("<loc>https://example.com/a/index.amp.html</loc>"));
+        assert!(!sitemap.contains("https://example.com/b/"));
+        assert!(!sitemap.contains("https://example.com/post/"));
+        assert!(!sitemap.contains(".xml</loc>"));
+        assert!(sitemap.contains("<url><loc>https://example.com/tags/</loc></url>"));
+        assert!(sitemap.contains("<url><loc>https://example.com/tags/rust/</loc></url>"));
+    }
+
+    #[test]
+    fn should_not_override_user_robots() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let config_file = dir.join("Staple.toml");
+        let config = std::fs::read_to_string(&config_file)?
+            .replace(r#"domain = """#, r#"domain = "https://example.com""#);
+        std::fs::write(&config_file, &config)?;
+        build(&dir, false, None)?;
+        assert!(dir.join("public/sitemap.xml").exists());
+        assert_eq!(
+            "User-agent: *\nDisallow:\n\nSitemap: https://example.com/sitemap.xml\n",
+            std::fs::read_to_string(dir.join("public/robots.txt"))?
+        );
+
+        std::fs::write(dir.join("templates/staple/robots.txt"), "Disallow: /")?;
+        let options = AddOptions {
+            title: "robots".to_owned(),
+            url: Some("robots.txt".to_owned()),
+            template: Some("robots.txt".to_owned()),
+            draw: false,
+            data: true,
+        };
+        add(&dir, options)?;
+        build(&dir, false, None)?;
+        assert_eq!(
+            "Disallow: /",
+            std::fs::read_to_string(dir.join("public/robots.txt"))?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{
+    config::Config,
+    data::PageInfo,
+    util::{hash::hash_bytes, slug::slugify},
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// length of hash used as slug of terms without any letter or digit, like `++`
+const HASH_SLUG_LENGTH: usize = 8;
+
+/// all terms of a taxonomy configured in `[[taxonomies]]`
+#[derive(Debug, Serialize)]
+pub struct TaxonomyIndex<'a> {
+    pub name: String,
+    pub url: String,
+    pub terms: Vec<Term<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Term<'a> {
+    pub name: String,
+    pub slug: String,
+    pub url: String,
+    pub pages: Vec<&'a PageInfo>,
+}
+
+/// group pages by terms of each taxonomy, terms are sorted by name and pages keep their order.
+pub fn build<'a>(
+    config: &Config,
+    articles: &[&'a PageInfo],
+) -> BTreeMap<String, TaxonomyIndex<'a>> {
+    config
+        .taxonomies
+        .iter()
+        .map(|taxonomy| {
+            let prefix = taxonomy.url_prefix();
+            let mut terms: BTreeMap<String, Term<'a>> = BTreeMap::new();
+            for article in articles.iter().copied() {
+                for name in article.terms(&taxonomy.name) {
+                    let slug = term_slug(&name);
+                    let term = terms.entry(slug.clone()).or_insert_with(|| Term {
+                        url: format!("/{}/{}/", prefix, slug),
+                        name,
+                        slug,
+                        pages: vec![],
+                    });
+                    term.pages.push(article);
+                }
+            }
+            let index = TaxonomyIndex {
+                name: taxonomy.name.clone(),
+                url: format!("/{}/", prefix),
+                terms: terms.into_values().collect(),
+            };
+            (taxonomy.name.clone(), index)
+        })
+        .collect()
+}
+
+/// slug of term in its url, hash of name is used if nothing is left after slugifying
+pub fn term_slug(name: &str) -> String {
+    let slug = slugify(name);
+    if slug.is_empty() {
+        hash_bytes(name)[..HASH_SLUG_LENGTH].to_string()
+    } else {
+        slug
+    }
+}
+
+/// terms merged into one url since they have the same slug, like `C++` and `c`. terms only
+/// differing in letter case are the same term.
+pub fn collisions(config: &Config, articles: &[PageInfo]) -> Vec<(String, Vec<String>)> {
+    let mut collisions = vec![];
+    for taxonomy in &config.taxonomies {
+        let prefix = taxonomy.url_prefix();
+        let mut slugs: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        for article in articles {
+            for name in article.terms(&taxonomy.name) {
+                slugs
+                    .entry(term_slug(&name))
+                    .or_default()
+                    .entry(name.to_lowercase())
+                    .or_insert(name);
+            }
+        }
+        collisions.extend(slugs.into_iter().filter(|(_, names)| names.len() > 1).map(
+            |(slug, names)| {
+                (
+                    format!("/{}/{}/", prefix, slug),
+                    names.into_values().collect(),
+                )
+            },
+        ));
+    }
+    collisions
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        command::{
+            add::{add, AddOptions},
+            build::build,
+        },
+        config::{Config, Taxonomy},
+        data::PageInfo,
+        taxonomy::{collisions, term_slug, HASH_SLUG_LENGTH},
+        test::setup,
+    };
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn page(url: &str, tags: serde_json::Value) -> PageInfo {
+        let mut data = std::collections::HashMap::new();
+        data.insert("tags".to_string(), tags);
+        PageInfo {
+            file: "".to_string(),
+            url: url.to_string(),
+            title: "".to_string(),
+            template: "".to_string(),
+            draw: false,
+            datetime: Utc::now().into(),
+            data,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn should_group_pages_by_term() {
+        let mut config = Config::default();
+        config.taxonomies.push(Taxonomy {
+            name: "tags".to_string(),
+            url: None,
+            index_template: None,
+            template: None,
+        });
+        let pages = [
+            page("a", json!(["Rust", "Web Dev"])),
+            page("b", json!("rust, life")),
+            page("c", json!(null)),
+        ];
+        let taxonomies = super::build(&config, &pages.iter().collect::<Vec<_>>());
+        let tags = &taxonomies["tags"];
+        assert_eq!("/tags/", tags.url);
+        let terms: Vec<(&str, &str, usize)> = tags
+            .terms
+            .iter()
+            .map(|term| (term.name.as_str(), term.url.as_str(), term.pages.len()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("life", "/tags/life/", 1),
+                ("Rust", "/tags/rust/", 2),
+                ("Web Dev", "/tags/web-dev/", 1)
+            ],
+            terms
+        );
+    }
+
+    #[test]
+    fn should_give_terms_non_empty_slugs_and_report_collisions() {
+        let mut config = Config::default();
+        config.taxonomies.push(Taxonomy {
+            name: "tags".to_string(),
+            url: None,
+            index_template: None,
+            template: None,
+        });
+        let pages = [
+            page("a", json!(["++", "C++", "Rust"])),
+            page("b", json!(["c", "rust", "!!!"])),
+        ];
+        let taxonomies = super::build(&config, &pages.iter().collect::<Vec<_>>());
+        let slugs: Vec<&str> = taxonomies["tags"]
+            .terms
+            .iter()
+            .map(|term| term.slug.as_str())
+            .collect();
+        assert_eq!(4, slugs.len());
+        assert!(slugs.iter().all(|slug| !slug.is_empty()));
+        assert_ne!(term_slug("++"), term_slug("!!!"));
+        assert_eq!(HASH_SLUG_LENGTH, term_slug("++").len());
+
+        assert_eq!(
+            vec![(
+                "/tags/c/".to_string(),
+                vec!["c".to_string(), "C++".to_string()]
+            )],
+            collisions(&config, &pages)
+        );
+    }
+
+    #[test]
+    fn should_render_taxonomy_pages() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = setup();
+        crate::command::init::init(&dir)?;
+        let config_file = dir.join("Staple.toml");
+        let config = std::fs::read_to_string(&config_file)?;
+        std::fs::write(
+            &config_file,
+            format!(
+                "{}\n{}",
+                config,
+                r#"
+                [[taxonomies]]
+                name = "tags"
+                index_template = "tags.html"
+                template = "tag.html"
+                "#
+            ),
+        )?;
+        std::fs::write(
+            dir.join("templates/staple/tags.html"),
+            "{% for term in taxonomy.terms %}{{ term.name }}{% endfor %}",
+        )?;
+        std::fs::write(
+            dir.join("templates/staple/tag.html"),
+            "{% for page in term.pages %}{{ page.title }}{% endfor %}",
+        )?;
+        std::fs::write(
+            dir.join("templates/staple/article.html"),
+            "{{ taxonomies.tags.terms | length }}",
+        )?;
+        let options = AddOptions {
+            title: "one".to_owned(),
+            url: None,
+            template: None,
+            draw: false,
+            data: false,
+        };
+        add(&dir, options)?;
+        let article = dir.join("data/one.md");
+        let content = std::fs::read_to_string(&article)?;
+        std::fs::write(&article, format!(" - tags = [\"rust\"]\n{}", content))?;
+        build(&dir, false, None)?;
+
+        assert_eq!(
+            "rust",
+            std::fs::read_to_string(dir.join("public/tags/index.html"))?
+        );
+        assert_eq!(
+            "one",
+            std::fs::read_to_string(dir.join("public/tags/rust/index.html"))?
+        );
+        assert_eq!(
+            "1",
+            std::fs::read_to_string(dir.join("public/one/index.html"))?
+        );
+        Ok(())
+    }
+}
@@ -1,15 +1,42 @@
-use crate::{config::Config, error::StapleError};
+use crate::{
+    assets::{self, Assets, MANIFEST_FILE},
+    cache::{BuildCache, PageCache, StylesheetCache},
+    config::{Config, Sass},
+    error::StapleError,
+    images, minify, sass,
+    util::hash::{hash_bytes, hash_file},
+};
 
-use std::path::{Path, PathBuf};
-use tera::{Context, Tera};
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tera::{ast::Node, Context, Tera};
+use walkdir::WalkDir;
 
 use serde::Serialize;
 
 use crate::{
-    constants::{LIVE_RELOAD_CODE, PUBLIC_FOLDER, RENDER_FOLDER},
-    data::{DataFile, PageInfo},
+    constants::{LIVE_RELOAD_CODE, PUBLIC_FOLDER},
+    data::{
+        output_file_name,
+        section::{is_section_index, Section, Sections},
+        DataFile, MarkdownRenderer, PageInfo,
+    },
+    paginator::{self, PaginateOptions, Paginator},
+    taxonomy::{self, TaxonomyIndex, Term},
 };
 
+static TAG_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)\{\{.*?\}\}|\{%.*?%\}").expect("invalid regex"));
+static SECTION_VARIABLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(section|ancestors)\b").expect("invalid regex"));
+
 #[derive(Debug, Serialize)]
 pub struct DevelopData {
     live_reload: &'static str,
@@ -22,39 +49,114 @@ impl DevelopData {
     }
 }
 
+/// data shared by all rendered pages
+#[derive(Debug, Serialize)]
+pub struct SiteData<'a> {
+    config: &'a Config,
+    develop: DevelopData,
+    /// pages except index pages of sections, which are reached via `section.index`
+    pages: Vec<&'a PageInfo>,
+    /// all pages to render
+    #[serde(skip)]
+    articles: &'a [PageInfo],
+    taxonomies: BTreeMap<String, TaxonomyIndex<'a>>,
+    #[serde(skip)]
+    sections: Option<(&'a Sections, Section<'a>)>,
+}
+
+impl<'a> SiteData<'a> {
+    pub fn new(
+        pages: &'a [PageInfo],
+        config: &'a Config,
+        is_develop_mode: bool,
+        renderer: &'a MarkdownRenderer,
+    ) -> Self {
+        let listed: Vec<&PageInfo> = pages
+            .iter()
+            .filter(|page| !is_section_index(&page.file))
+            .collect();
+        SiteData {
+            config,
+            develop: DevelopData::new(is_develop_mode),
+            taxonomies: taxonomy::build(config, &listed),
+            pages: listed,
+            articles: pages,
+            sections: renderer
+                .sections
+                .as_ref()
+                .map(|sections| (sections, sections.build(pages))),
+        }
+    }
+
+    /// section of page, or the one it is index page of, along with index pages of sections
+    /// containing the page from the outermost
+    fn section_of(&self, file: &str) -> (Option<&Section<'a>>, Vec<&'a PageInfo>) {
+        let found = self.sections.as_ref().and_then(|(sections, root)| {
+            let (section, mut ancestors) = root.find(&sections.path_of(file)?)?;
+            ancestors.retain(|index| index.file != file);
+            Some((section, ancestors))
+        });
+        match found {
+            Some((section, ancestors)) => (Some(section), ancestors),
+            None => (None, vec![]),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct RenderData<'a> {
     page: DataFile,
-    config: &'a Config,
-    develop: &'a DevelopData,
-    pages: &'a [PageInfo],
+    paginator: Option<&'a Paginator<'a>>,
+    /// section of page, or the one it is index page of
+    section: Option<&'a Section<'a>>,
+    /// index pages of sections containing page from the outermost, for breadcrumbs
+    ancestors: Vec<&'a PageInfo>,
+    #[serde(flatten)]
+    site: &'a SiteData<'a>,
 }
 
 impl<'a> RenderData<'a> {
     pub fn new(
         page: DataFile,
-        pages: &'a [PageInfo],
-        config: &'a Config,
-        develop: &'a DevelopData,
+        paginator: Option<&'a Paginator<'a>>,
+        article: &PageInfo,
+        site: &'a SiteData<'a>,
     ) -> Self {
+        let (section, ancestors) = site.section_of(&article.file);
         RenderData {
             page,
-            pages,
-            config,
-            develop,
+            paginator,
+            section,
+            ancestors,
+            site,
         }
     }
 }
 
+/// data of taxonomy index page and term page, `term` is absent in index page
+#[derive(Debug, Serialize)]
+pub struct TaxonomyRenderData<'a> {
+    taxonomy: &'a TaxonomyIndex<'a>,
+    term: Option<&'a Term<'a>>,
+    #[serde(flatten)]
+    site: &'a SiteData<'a>,
+}
+
 #[derive(Debug)]
 pub struct Template {
     working_path: PathBuf,
     name: String,
     tera: Tera,
+    pub(crate) renderer: Arc<MarkdownRenderer>,
+    assets: Arc<Assets>,
+    /// whether html pages and statics are minified
+    minify: bool,
 }
 
 impl Template {
-    pub fn new(path: impl AsRef<Path>, name: String) -> Result<Self, StapleError> {
+    pub fn new(path: impl AsRef<Path>, config: &Config) -> Result<Self, StapleError> {
+        let name = config.get_theme()?;
+        debug!("theme is {}", name);
         let buf = path
             .as_ref()
             .canonicalize()
@@ -63,68 +165,409 @@ impl Template {
         let theme_folder = format!("{}/templates/{}/*", root, name);
         debug!("theme folder is {}", theme_folder);
         let mut tera = Tera::new(&theme_folder)?;
+        let renderer = Arc::new(MarkdownRenderer::load(&path, config)?);
         tera.register_filter("not_field", crate::util::filter::not_field);
-        tera.register_filter("markdown", crate::util::filter::markdown);
-        tera.register_function("page_detail", crate::util::filter::page_detail);
+        let markdown = renderer.clone();
+        tera.register_filter("markdown", move |value: &_, attributes: &_| {
+            crate::util::filter::markdown(value, attributes, &markdown)
+        });
+        let markdown = renderer.clone();
+        tera.register_function("page_detail", move |args: &_| {
+            crate::util::filter::page_detail(args, &markdown)
+        });
+        let markdown = renderer.clone();
+        tera.register_function("resize_image", move |args: &_| {
+            crate::util::filter::resize_image(args, &markdown)
+        });
+        let assets = Arc::new(Assets::new(config));
+        let statics = assets.clone();
+        tera.register_function("asset_url", move |args: &_| {
+            crate::util::filter::asset_url(args, &statics)
+        });
         Ok(Template {
             working_path: path.as_ref().to_path_buf(),
             name,
             tera,
+            renderer,
+            assets,
+            minify: config.build.minify,
         })
     }
 
     pub fn render(
-        self,
-        articles: Vec<PageInfo>,
+        &self,
+        articles: &[PageInfo],
         config: &Config,
         is_develop_mode: bool,
+        cache: &BuildCache,
+        current: &mut BuildCache,
     ) -> Result<(), StapleError> {
-        Template::remove_folder(self.working_path.join(RENDER_FOLDER))?;
-        std::fs::create_dir(self.working_path.join(RENDER_FOLDER))?;
+        let output_folder = self.working_path.join(PUBLIC_FOLDER);
+        if cache.is_empty() {
+            Template::remove_folder(&output_folder)?;
+        }
+        std::fs::create_dir_all(&output_folder)?;
+        // statics go first so that pages get their fingerprinted urls
+        self.copy_statics_folder(config, cache, current)?;
+        self.copy_statics(config, cache, current)?;
+        if config.site.fingerprint {
+            current.write(cache, &output_folder, MANIFEST_FILE, self.assets.manifest())?;
+        }
+
+        let site = SiteData::new(articles, config, is_develop_mode, &self.renderer);
+        let pages_digest = hash_bytes(serde_json::to_vec(articles)?);
+        let assets_digest = hash_bytes(self.assets.manifest());
+        let digests: HashMap<&str, Option<TemplateDigest>> = articles
+            .iter()
+            .map(|article| article.template.as_str())
+            .chain(config.output_formats.values().map(|f| f.template.as_str()))
+            .unique()
+            .map(|name| (name, self.digest(name).ok()))
+            .collect();
+
+        let results: Vec<(&PageInfo, Result<PageOutputs, StapleError>)> = articles
+            .par_iter()
+            .map(|article| {
+                let result = self.render_or_skip(
+                    &site,
+                    article,
+                    cache,
+                    &digests,
+                    &pages_digest,
+                    &assets_digest,
+                );
+                (article, result)
+            })
+            .collect();
 
-        // todo can be parallel rendering
-        for article in articles.iter() {
-            self.render_article(config, article, &articles, is_develop_mode)?;
+        let mut errors = vec![];
+        for (article, result) in results {
+            match result {
+                Ok(entries) => current.pages.extend(entries),
+                Err(e) => errors.push((article.file.clone(), e)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(StapleError::PagesError(errors));
         }
 
-        self.copy_statics_folder(config)?;
-        self.copy_statics(config)?;
+        self.render_taxonomies(&site, cache, current)?;
+        self.copy_bundle_assets(articles, cache, current)
+    }
 
-        Template::remove_folder(self.working_path.join(PUBLIC_FOLDER))?;
-        std::fs::rename(
-            self.working_path.join(RENDER_FOLDER),
-            self.working_path.join(PUBLIC_FOLDER),
-        )?;
-        Ok(())
+    /// render article unless its inputs are the same as last build.
+    /// article without digest of template is always rendered.
+    /// paginated article produces one output for each page, plus one for each output format.
+    fn render_or_skip(
+        &self,
+        site: &SiteData,
+        article: &PageInfo,
+        cache: &BuildCache,
+        digests: &HashMap<&str, Option<TemplateDigest>>,
+        pages_digest: &str,
+        assets_digest: &str,
+    ) -> Result<PageOutputs, StapleError> {
+        let file_digest = hash_file(&article.file)?;
+
+        let mut entries = vec![];
+        for job in render_jobs(site, article)? {
+            let RenderJob {
+                paginator,
+                template,
+                output,
+            } = &job;
+            let digest = digests.get(template.as_str()).and_then(Option::as_ref);
+            let uses_pages = paginator.is_some() || digest.map(|d| d.uses_pages).unwrap_or(true);
+            let key = match digest {
+                Some(digest) => hash_bytes(
+                    [
+                        file_digest.as_str(),
+                        &digest.hash,
+                        self.renderer
+                            .shortcodes
+                            .as_ref()
+                            .map(|shortcodes| shortcodes.digest.as_str())
+                            .unwrap_or_default(),
+                        if uses_pages { pages_digest } else { "" },
+                        if digest.uses_assets {
+                            assets_digest
+                        } else {
+                            ""
+                        },
+                        &paginator
+                            .as_ref()
+                            .map(|p| p.current.to_string())
+                            .unwrap_or_default(),
+                    ]
+                    .concat(),
+                ),
+                None => String::new(),
+            };
+            let previous = cache.pages.get(output);
+            let is_volatile = digest.map(|digest| digest.volatile).unwrap_or(true);
+            let entry = match previous {
+                Some(entry)
+                    if !is_volatile
+                        && entry.key == key
+                        && self.working_path.join(PUBLIC_FOLDER).join(output).exists()
+                        && images::is_up_to_date(&entry.images) =>
+                {
+                    debug!("skip unchanged article {}({})", &article.title, output);
+                    if let Some(processor) = &self.renderer.images {
+                        processor.register(&entry.images);
+                    }
+                    entry.clone()
+                }
+                _ => self.render_article(site, article, &job, previous, key)?,
+            };
+            entries.push((job.output, entry));
+        }
+        Ok(entries)
     }
 
-    pub fn render_article<'a>(
+    fn render_article(
         &self,
-        config: &Config,
+        site: &SiteData,
         article: &PageInfo,
-        articles: &'a [PageInfo],
-        is_develop_mode: bool,
-    ) -> Result<(), StapleError> {
-        info!("rendering article {}({})", &article.title, &article.url);
-        let debug_data = DevelopData::new(is_develop_mode);
+        job: &RenderJob,
+        previous: Option<&PageCache>,
+        key: String,
+    ) -> Result<PageCache, StapleError> {
+        info!("rendering article {}({})", &article.title, &job.output);
+        let (result, images) = images::record(|| {
+            self.render_to_string(site, article, job.paginator.as_ref(), &job.template)
+        });
+        let result = self.minify_page(&job.output, result?);
+        let output_file = self.working_path.join(PUBLIC_FOLDER).join(&job.output);
+
+        let output = hash_bytes(&result);
+        let is_unchanged =
+            previous.map(|page| page.output == output).unwrap_or(false) && output_file.exists();
+        if !is_unchanged {
+            if let Some(p) = output_file.parent() {
+                if !p.exists() {
+                    std::fs::create_dir_all(p)?;
+                }
+            }
+            std::fs::write(output_file, result.as_bytes())?;
+        }
+        Ok(PageCache {
+            source: article.file.clone(),
+            key,
+            output,
+            images,
+        })
+    }
 
-        let full_article = article.to_full_article()?;
+    fn minify_page(&self, output_file: &str, content: String) -> String {
+        if self.minify && minify::is_html(output_file) {
+            minify::html(&content)
+        } else {
+            content
+        }
+    }
 
-        let data = RenderData::new(full_article, articles, config, &debug_data);
+    fn render_to_string(
+        &self,
+        site: &SiteData,
+        article: &PageInfo,
+        paginator: Option<&Paginator>,
+        template: &str,
+    ) -> Result<String, StapleError> {
+        let full_article = article.to_full_article(&self.renderer)?;
+        let data = RenderData::new(full_article, paginator, article, site);
         let context = Context::from_serialize(&data).expect("cannot serialize");
-        let result = self.tera.render(data.page.template(), &context)?;
-        let url = article.output_file_name();
-        let output_file = self.working_path.join(RENDER_FOLDER).join(url);
+        Ok(self.tera.render(template, &context)?)
+    }
 
-        if let Some(p) = output_file.parent() {
-            if !p.exists() {
-                std::fs::create_dir_all(p)?;
+    /// render all pages and taxonomy pages in memory without writing output,
+    /// return rendered outputs and problems found in each of them.
+    pub fn check(&self, site: &SiteData) -> (RenderedPages, Vec<(String, StapleError)>) {
+        let results: Vec<(&PageInfo, Result<RenderedPages, StapleError>)> = site
+            .articles
+            .par_iter()
+            .map(|article| (article, self.check_page(site, article)))
+            .collect();
+        let mut outputs = vec![];
+        let mut errors = vec![];
+        for (article, result) in results {
+            match result {
+                Ok(pages) => outputs.extend(pages),
+                Err(e) => errors.push((article.file.clone(), e)),
+            }
+        }
+        for (url, result) in self.render_taxonomy_pages(site) {
+            match result {
+                Ok(content) => outputs.push((output_file_name(&url), content)),
+                Err(e) => errors.push((url, e.into())),
+            }
+        }
+        (outputs, errors)
+    }
+
+    fn check_page(
+        &self,
+        site: &SiteData,
+        article: &PageInfo,
+    ) -> Result<RenderedPages, StapleError> {
+        let mut outputs = vec![];
+        for job in render_jobs(site, article)? {
+            if self.tera.get_template(&job.template).is_err() {
+                return Err(StapleError::TemplateNotFound(job.template));
+            }
+            let content =
+                self.render_to_string(site, article, job.paginator.as_ref(), &job.template)?;
+            outputs.push((job.output, content));
+        }
+        Ok(outputs)
+    }
+
+    /// output files of theme's statics folder and `[[statics]]` entries, including fingerprinted
+    /// copies written by `copy_asset` and `compile_stylesheet`
+    pub fn statics_outputs(&self, config: &Config) -> Vec<String> {
+        let statics_folder = self
+            .working_path
+            .join("templates")
+            .join(&config.site.theme)
+            .join("statics");
+        let options = Sass {
+            minify: config.sass.minify || self.minify,
+        };
+        let mut outputs: Vec<String> = WalkDir::new(&statics_folder)
+            .into_iter()
+            .flat_map(|e| e.ok())
+            .filter(|de| de.path().is_file() && !sass::is_partial(de.path()))
+            .flat_map(|de| {
+                let relative = de.path().strip_prefix(&statics_folder).ok()?;
+                let output = Path::new("statics").join(relative);
+                let is_sass = sass::is_sass(&output);
+                let output = if is_sass {
+                    sass::output_file(&output)
+                } else {
+                    output
+                };
+                let digest = match (config.site.fingerprint, is_sass) {
+                    (false, _) => None,
+                    (true, true) => sass::compile(de.path(), &options)
+                        .ok()
+                        .map(|(css, _)| hash_bytes(css)),
+                    (true, false) => hash_file(de.path()).ok(),
+                };
+                Some(assets::outputs(output.to_str()?, digest.as_deref()))
+            })
+            .flatten()
+            .collect();
+        for statics in &config.statics {
+            let fingerprint = config.site.fingerprint && statics.fingerprint;
+            for (from, output) in statics.files(&self.working_path).unwrap_or_default() {
+                let digest = fingerprint.then(|| hash_file(&from).ok()).flatten();
+                outputs.extend(assets::outputs(&output, digest.as_deref()));
+            }
+        }
+        outputs
+    }
+
+    /// source and output files of assets in page bundles
+    pub fn bundle_assets(&self, articles: &[PageInfo]) -> Vec<(PathBuf, String)> {
+        articles
+            .iter()
+            .filter_map(|article| self.renderer.bundle(&article.file, &article.url))
+            .flat_map(|bundle| bundle.outputs())
+            .collect()
+    }
+
+    fn render_taxonomy_pages(&self, site: &SiteData) -> Vec<(String, Result<String, tera::Error>)> {
+        let mut jobs = vec![];
+        for taxonomy in &site.config.taxonomies {
+            let index = &site.taxonomies[&taxonomy.name];
+            if let Some(template) = &taxonomy.index_template {
+                jobs.push((template, index, None));
+            }
+            if let Some(template) = &taxonomy.template {
+                jobs.extend(index.terms.iter().map(|term| (template, index, Some(term))));
             }
         }
-        std::fs::write(output_file, result.as_bytes()).map_err(StapleError::IoError)
+
+        jobs.into_par_iter()
+            .map(|(template, taxonomy, term)| {
+                let url = term.map(|term| &term.url).unwrap_or(&taxonomy.url);
+                info!("rendering taxonomy page {}", url);
+                let data = TaxonomyRenderData {
+                    taxonomy,
+                    term,
+                    site,
+                };
+                let context = Context::from_serialize(&data).expect("cannot serialize");
+                (url.to_string(), self.tera.render(template, &context))
+            })
+            .collect()
+    }
+
+    fn render_taxonomies(
+        &self,
+        site: &SiteData,
+        cache: &BuildCache,
+        current: &mut BuildCache,
+    ) -> Result<(), StapleError> {
+        let results = self.render_taxonomy_pages(site);
+
+        let output_folder = self.working_path.join(PUBLIC_FOLDER);
+        let mut errors = vec![];
+        for (url, result) in results {
+            match result {
+                Ok(content) => {
+                    let output_file = output_file_name(&url);
+                    let content = self.minify_page(&output_file, content);
+                    current.write(cache, &output_folder, &output_file, content)?;
+                }
+                Err(e) => errors.push((url, StapleError::RenderError(e))),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(StapleError::PagesError(errors));
+        }
+        Ok(())
     }
 
-    fn copy_statics_folder(&self, config: &Config) -> Result<(), StapleError> {
+    /// digest of template and all templates it depends on via `extends`, `include` and `import`.
+    fn digest(&self, name: &str) -> Result<TemplateDigest, StapleError> {
+        let mut visited = HashSet::new();
+        let mut pending = vec![name.to_string()];
+        let mut sources = String::new();
+        while let Some(name) = pending.pop() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            let template = self.tera.get_template(&name)?;
+            match &template.path {
+                Some(path) => sources.push_str(&std::fs::read_to_string(path)?),
+                None => sources.push_str(&name),
+            }
+            pending.extend(template.parents.iter().cloned());
+            pending.extend(
+                template
+                    .imported_macro_files
+                    .iter()
+                    .map(|(file, _)| file.clone()),
+            );
+            find_includes(&template.ast, &mut pending);
+        }
+        Ok(TemplateDigest {
+            hash: hash_bytes(&sources),
+            uses_pages: sources.contains("pages")
+                || sources.contains("taxonomies")
+                || uses_sections(&sources),
+            volatile: sources.contains("page_detail"),
+            uses_assets: sources.contains("asset_url"),
+        })
+    }
+
+    fn copy_statics_folder(
+        &self,
+        config: &Config,
+        cache: &BuildCache,
+        current: &mut BuildCache,
+    ) -> Result<(), StapleError> {
         info!("copy template static folder");
         let statics_folder = self
             .working_path
@@ -132,15 +575,122 @@ impl Template {
             .join(&config.site.theme)
             .join("statics");
         if statics_folder.exists() {
-            debug!("statics folder exist, copy to render folder");
-            copy_dir::copy_dir(
-                statics_folder,
-                self.working_path.join(RENDER_FOLDER).join("statics"),
-            )?;
+            debug!("statics folder exist, copy to output folder");
+            let files = WalkDir::new(&statics_folder)
+                .into_iter()
+                .flat_map(|e| e.ok())
+                .filter(|de| de.path().is_file());
+            for file in files {
+                let relative = file
+                    .path()
+                    .strip_prefix(&statics_folder)
+                    .expect("static file is not in statics folder");
+                let output = Path::new("statics").join(relative);
+                if sass::is_partial(file.path()) {
+                    continue;
+                } else if sass::is_sass(file.path()) {
+                    let output = sass::output_file(&output);
+                    self.compile_stylesheet(file.path(), output, config, cache, current)?;
+                } else {
+                    let fingerprint = config.site.fingerprint;
+                    self.copy_asset(file.path(), output, fingerprint, cache, current)?;
+                }
+            }
         }
         Ok(())
     }
 
+    fn copy_bundle_assets(
+        &self,
+        articles: &[PageInfo],
+        cache: &BuildCache,
+        current: &mut BuildCache,
+    ) -> Result<(), StapleError> {
+        for (from, to) in self.bundle_assets(articles) {
+            // reported as duplicate output, pages and statics are kept
+            if current.pages.contains_key(&to) || current.statics.contains_key(&to) {
+                continue;
+            }
+            self.copy_static_file(from, to, cache, current)?;
+        }
+        Ok(())
+    }
+
+    /// compile stylesheet into output folder unless it and its imports are the same as last build.
+    fn compile_stylesheet(
+        &self,
+        from: &Path,
+        to: impl AsRef<Path>,
+        config: &Config,
+        cache: &BuildCache,
+        current: &mut BuildCache,
+    ) -> Result<(), StapleError> {
+        let output = to.as_ref().to_str().expect("invalid file path").to_string();
+        let output_folder = self.working_path.join(PUBLIC_FOLDER);
+        let previous = cache.stylesheets.get(&output).filter(|previous| {
+            sass::digest(&previous.sources).as_ref() == Some(&previous.digest)
+                && output_folder.join(&output).exists()
+                && output_folder.join(&previous.output).exists()
+        });
+        let stylesheet = match previous {
+            Some(previous) => previous.clone(),
+            None => {
+                info!("compiling stylesheet {}", output);
+                let options = Sass {
+                    minify: config.sass.minify || self.minify,
+                };
+                let (css, sources) = sass::compile(from, &options)?;
+                let digest = config.site.fingerprint.then(|| hash_bytes(&css));
+                let files = assets::outputs(&output, digest.as_deref());
+                for file in &files {
+                    let target = output_folder.join(file);
+                    if let Some(p) = target.parent() {
+                        std::fs::create_dir_all(p)?;
+                    }
+                    std::fs::write(target, &css)?;
+                }
+                StylesheetCache {
+                    digest: sass::digest(&sources).unwrap_or_default(),
+                    sources,
+                    output: files.last().cloned().unwrap_or_else(|| output.clone()),
+                }
+            }
+        };
+        for file in [&output, &stylesheet.output] {
+            current
+                .statics
+                .insert(file.clone(), stylesheet.digest.clone());
+        }
+        self.assets
+            .insert(output.clone(), stylesheet.output.clone());
+        current.stylesheets.insert(output, stylesheet);
+        Ok(())
+    }
+
+    /// copy statics file, along with a copy having content hash in its name if `fingerprint` is set
+    fn copy_asset(
+        &self,
+        from: impl AsRef<Path>,
+        to: impl AsRef<Path>,
+        fingerprint: bool,
+        cache: &BuildCache,
+        current: &mut BuildCache,
+    ) -> Result<(), StapleError> {
+        let output = to.as_ref().to_str().expect("invalid file path").to_string();
+        let digest = if fingerprint {
+            Some(hash_file(&from)?)
+        } else {
+            None
+        };
+        let files = assets::outputs(&output, digest.as_deref());
+        for file in &files {
+            self.copy_static_file(&from, file, cache, current)?;
+        }
+        let file = files.last().cloned().unwrap_or_else(|| output.clone());
+        self.assets.insert(output, file);
+        Ok(())
+    }
+
     pub fn remove_folder(path: impl AsRef<Path>) -> Result<(), StapleError> {
         let buf = path.as_ref();
         if buf.exists() {
@@ -151,14 +701,165 @@ impl Template {
         }
     }
 
-    pub fn copy_statics(&self, config: &Config) -> Result<(), StapleError> {
-        let render_folder = self.working_path.join(RENDER_FOLDER);
+    pub fn copy_statics(
+        &self,
+        config: &Config,
+        cache: &BuildCache,
+        current: &mut BuildCache,
+    ) -> Result<(), StapleError> {
         for statics in &config.statics {
-            let from = self.working_path.join(&statics.from);
-            let to = render_folder.join(&statics.to);
             info!("coping statics from {} to {}", &statics.from, &statics.to);
-            std::fs::copy(from, to)?;
+            let fingerprint = config.site.fingerprint && statics.fingerprint;
+            for (from, to) in statics.files(&self.working_path)? {
+                self.copy_asset(from, to, fingerprint, cache, current)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// copy file into output folder if its content changes since last build, css and js are
+    /// minified if enabled.
+    fn copy_static_file(
+        &self,
+        from: impl AsRef<Path>,
+        to: impl AsRef<Path>,
+        cache: &BuildCache,
+        current: &mut BuildCache,
+    ) -> Result<(), StapleError> {
+        let hash = hash_file(&from)?;
+        let output = to.as_ref().to_str().expect("invalid file path").to_string();
+        let target = self.working_path.join(PUBLIC_FOLDER).join(&output);
+        if cache.statics.get(&output) != Some(&hash) || !target.exists() {
+            if let Some(p) = target.parent() {
+                std::fs::create_dir_all(p)?;
+            }
+            match self
+                .minify
+                .then(|| minify::statics(from.as_ref()))
+                .flatten()
+            {
+                Some(content) => std::fs::write(target, content)?,
+                None => {
+                    std::fs::copy(from, target)?;
+                }
+            }
         }
+        current.statics.insert(output, hash);
         Ok(())
     }
 }
+
+/// paginators of article, or a single `None` if article is not paginated
+fn paginators<'a>(
+    site: &'a SiteData,
+    article: &PageInfo,
+) -> Result<Vec<Option<Paginator<'a>>>, StapleError> {
+    Ok(match PaginateOptions::from_page(article)? {
+        Some(options) => paginator::paginate(article, &options, &site.pages)?
+            .into_iter()
+            .map(Some)
+            .collect(),
+        None => vec![None],
+    })
+}
+
+/// one output file of a page and the template rendering it
+struct RenderJob<'a> {
+    paginator: Option<Paginator<'a>>,
+    template: String,
+    output: String,
+}
+
+/// outputs of page, one for each paginated page and one for each of its output formats
+fn render_jobs<'a>(
+    site: &'a SiteData,
+    article: &PageInfo,
+) -> Result<Vec<RenderJob<'a>>, StapleError> {
+    let mut jobs: Vec<RenderJob> = paginators(site, article)?
+        .into_iter()
+        .map(|paginator| RenderJob {
+            output: match &paginator {
+                Some(paginator) => output_file_name(&paginator.url),
+                None => article.output_file_name(),
+            },
+            paginator,
+            template: article.template.clone(),
+        })
+        .collect();
+    for format in article.output_formats(site.config)? {
+        jobs.push(RenderJob {
+            paginator: None,
+            template: format.template.clone(),
+            output: format.output_file_name(&article.url),
+        });
+    }
+    Ok(jobs)
+}
+
+/// output files with their rendered content
+pub type RenderedPages = Vec<(String, String)>;
+
+/// output files of an article with their cache entries
+type PageOutputs = Vec<(String, PageCache)>;
+
+#[derive(Debug)]
+struct TemplateDigest {
+    hash: String,
+    /// whether the page list is accessed, pages need re-rendering once any page's metadata changes.
+    uses_pages: bool,
+    /// whether other data files are loaded via `page_detail`, pages need re-rendering every time.
+    volatile: bool,
+    /// whether `asset_url` is used, pages need re-rendering once fingerprinted files change.
+    uses_assets: bool,
+}
+
+/// whether `section` or `ancestors` variables are used in tags of template, `<section>` elements
+/// of html do not count
+fn uses_sections(sources: &str) -> bool {
+    TAG_REGEX
+        .find_iter(sources)
+        .any(|tag| SECTION_VARIABLE_REGEX.is_match(tag.as_str()))
+}
+
+fn find_includes(nodes: &[Node], includes: &mut Vec<String>) {
+    for node in nodes {
+        match node {
+            Node::Include(_, name) => includes.push(name.clone()),
+            Node::Block(_, block, _) => find_includes(&block.body, includes),
+            Node::MacroDefinition(_, definition, _) => find_includes(&definition.body, includes),
+            Node::FilterSection(_, section, _) => find_includes(&section.body, includes),
+            Node::Forloop(_, forloop, _) => {
+                find_includes(&forloop.body, includes);
+                if let Some(body) = &forloop.empty_body {
+                    find_includes(body, includes);
+                }
+            }
+            Node::If(condition, _) => {
+                for (_, _, body) in &condition.conditions {
+                    find_includes(body, includes);
+                }
+                if let Some((_, body)) = &condition.otherwise {
+                    find_includes(body, includes);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::template::uses_sections;
+
+    #[test]
+    fn should_detect_variables_of_sections() {
+        assert!(uses_sections(
+            "<ul>{% for p in section.pages %}{% endfor %}</ul>"
+        ));
+        assert!(uses_sections("{{ ancestors | length }}"));
+        assert!(!uses_sections(
+            "<section class=\"section\">{{ page.title }}</section>"
+        ));
+        assert!(!uses_sections("{{ page.subsections_count }}"));
+    }
+}
@@ -1,4 +1,8 @@
-use crate::data::{MarkdownContent, PageInfo};
+use crate::{
+    assets::Assets,
+    data::{MarkdownContent, MarkdownRenderer, PageInfo},
+    images::ResizeOp,
+};
 use chrono::{FixedOffset, Utc};
 use std::collections::HashMap;
 use tera::{Error, Value};
@@ -21,24 +25,28 @@ pub fn not_field(value: &Value, attributes: &HashMap<String, Value>) -> Result<V
     };
     let result = arr
         .into_iter()
-        .filter(|item| {
-            let field = item
-                .pointer(&get_json_pointer(&key))
-                .unwrap_or(&Value::Null);
-            if let Value::String(content) = field {
-                content.to_uppercase().eq("FALSE")
-            } else {
-                field.is_null() || field.eq(&Value::Bool(false))
-            }
-        })
+        .filter(|item| is_field_false(item, &key))
         .collect();
 
     Ok(result)
 }
 
+/// whether field of item is missing or false, `key` is split by dot like `data.page`
+pub fn is_field_false(item: &Value, key: &str) -> bool {
+    let field = item.pointer(&get_json_pointer(key)).unwrap_or(&Value::Null);
+    if let Value::String(content) = field {
+        content.to_uppercase().eq("FALSE")
+    } else {
+        field.is_null() || field.eq(&Value::Bool(false))
+    }
+}
+
 /// loading page detail of specific article while rendering.
 /// using this to add avalibility and flexibility to render cross-articles page like rss page or those need at least 2 articles full content.
-pub fn page_detail(args: &HashMap<String, Value>) -> Result<Value, tera::Error> {
+pub fn page_detail(
+    args: &HashMap<String, Value>,
+    renderer: &MarkdownRenderer,
+) -> Result<Value, tera::Error> {
     let file = match args.get("file") {
         Some(val) => match tera::from_value::<String>(val.clone()) {
             Ok(parsed_val) => parsed_val,
@@ -67,7 +75,7 @@ pub fn page_detail(args: &HashMap<String, Value>) -> Result<Value, tera::Error>
         data: HashMap::new(),
         description: None,
     }
-    .to_full_article();
+    .to_full_article(renderer);
     let data = match full_article {
         Ok(data) => data,
         Err(e) => return Err(Error::msg(format!("Error on loading page detail: {}", e))),
@@ -75,10 +83,72 @@ pub fn page_detail(args: &HashMap<String, Value>) -> Result<Value, tera::Error>
     serde_json::to_value(data).map_err(|_| Error::msg("Error on serializing page data into json"))
 }
 
+/// url of statics output, fingerprinted file is used once fingerprint is enabled
+pub fn asset_url(args: &HashMap<String, Value>, assets: &Assets) -> Result<Value, tera::Error> {
+    match args.get("path").and_then(Value::as_str) {
+        Some(path) => Ok(Value::String(assets.url(path))),
+        None => Err(Error::msg(
+            "Function `asset_url` requires string argument `path`",
+        )),
+    }
+}
+
+/// resize image into output folder, `path` is a file path relative to site folder or an url of
+/// statics, `op` is one of `scale`, `fit_width`, `fit_height`, `fit` and `fill`(default).
+/// return `url`, `width` and `height` of resized image.
+pub fn resize_image(
+    args: &HashMap<String, Value>,
+    renderer: &MarkdownRenderer,
+) -> Result<Value, tera::Error> {
+    let path = match args.get("path").and_then(Value::as_str) {
+        Some(path) => path,
+        None => {
+            return Err(Error::msg(
+                "Function `resize_image` requires string argument `path`",
+            ));
+        }
+    };
+    let size = |name: &str| match args.get(name) {
+        Some(value) => match value.as_u64() {
+            Some(size) => Ok(Some(size as u32)),
+            None => Err(Error::msg(format!(
+                "Function `resize_image` receive {}={} but it can only be a positive integer",
+                name, value
+            ))),
+        },
+        None => Ok(None),
+    };
+    let (width, height) = (size("width")?, size("height")?);
+    let op = match args.get("op") {
+        Some(op) => tera::from_value::<ResizeOp>(op.clone()).map_err(|_| {
+            Error::msg(format!("Function `resize_image` receive unknown op={}", op))
+        })?,
+        None => ResizeOp::Fill,
+    };
+
+    let processor = renderer
+        .images
+        .as_ref()
+        .ok_or_else(|| Error::msg("image processing is not available"))?;
+    let source = processor
+        .resolve(path)
+        .ok_or_else(|| Error::msg(format!("image `{}` does not exist", path)))?;
+    let image = processor
+        .resize(&source, op, width, height)
+        .map_err(|e| Error::msg(e.to_string()))?;
+    serde_json::to_value(image).map_err(|_| Error::msg("Error on serializing image into json"))
+}
+
 /// render text as markdown
-pub fn markdown(value: &Value, _attributes: &HashMap<String, Value>) -> Result<Value, tera::Error> {
+pub fn markdown(
+    value: &Value,
+    _attributes: &HashMap<String, Value>,
+    renderer: &MarkdownRenderer,
+) -> Result<Value, tera::Error> {
     if let Value::String(content) = value {
-        let html_content = MarkdownContent::new(content.to_owned()).html;
+        let html_content = MarkdownContent::render(content.to_owned(), renderer)
+            .map_err(|e| Error::msg(e.to_string()))?
+            .html;
         Ok(Value::String(html_content))
     } else {
         Ok(value.to_owned())
@@ -150,14 +220,14 @@ mod test {
     #[test]
     fn should_render_text_into_markdown() {
         let value = Value::String("hello".to_owned());
-        let result = markdown(&value, &HashMap::new()).expect("is not a ok");
+        let result = markdown(&value, &HashMap::new(), &Default::default()).expect("is not a ok");
         assert_eq!(Value::String("<p>hello</p>\n".to_owned()), result);
     }
 
     #[test]
     fn should_return_the_same_if_value_is_not_text() {
         let value = Value::Bool(true);
-        let result = markdown(&value, &HashMap::new()).expect("is not a ok");
+        let result = markdown(&value, &HashMap::new(), &Default::default()).expect("is not a ok");
         assert_eq!(Value::Bool(true), result);
     }
 }
@@ -0,0 +1,25 @@
+use crate::error::StapleError;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// hex encoded sha256 digest of content
+pub fn hash_bytes(content: impl AsRef<[u8]>) -> String {
+    format!("{:x}", Sha256::digest(content.as_ref()))
+}
+
+pub fn hash_file(path: impl AsRef<Path>) -> Result<String, StapleError> {
+    Ok(hash_bytes(std::fs::read(path)?))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::util::hash::hash_bytes;
+
+    #[test]
+    fn should_hash_content_as_hex_string() {
+        assert_eq!(
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+            hash_bytes("hello")
+        );
+    }
+}
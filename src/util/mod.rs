@@ -1,2 +1,5 @@
 pub mod filter;
+pub mod hash;
 pub mod lock;
+pub mod slug;
+pub mod xml;
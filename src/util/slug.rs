@@ -0,0 +1,26 @@
+/// turn text into url-safe slug, unicode letters like CJK are kept as they are.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::util::slug::slugify;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!("hello-world", slugify("Hello World"));
+        assert_eq!("rust-lang", slugify(" Rust_Lang! "));
+        assert_eq!("a-b", slugify("a -- b"));
+        assert_eq!("中文标签", slugify("中文标签"));
+        assert_eq!("c", slugify("C++"));
+    }
+}
@@ -0,0 +1,29 @@
+/// escape text to be placed in xml element or attribute
+pub fn escape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use crate::util::xml::escape;
+
+    #[test]
+    fn should_escape_xml_special_chars() {
+        assert_eq!(
+            "&lt;a href=&quot;/&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;",
+            escape(r#"<a href="/">Tom & Jerry's</a>"#)
+        );
+        assert_eq!("中文", escape("中文"));
+    }
+}